@@ -3,33 +3,98 @@
 // SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
 
 use std::{env, error::Error, process::{self, exit}};
-use cs_data::glasgow_data;
+use cs_data::{anonymize, glasgow_data, i18n::Locale, stalled::{self, StalledRules}};
+
+/// Remove an optional `--name value` pair from the argument list and return the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let pos = args.iter().position(|a| a == name)?;
+    if pos + 1 >= args.len() {
+        println!("The option {} needs a value!", name);
+        exit(1);
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Some(value)
+}
 
 fn run() -> Result<(), Box<dyn Error>> {
 
     // Parse arguments
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 4 {
-        println!("You need to provide the mouser fulfillment data CSV, production data CSV and an order ID as a command line parameters!");
-        exit(1);
+    let mut args: Vec<String> = env::args().collect();
+    let overrides = take_option(&mut args, "--overrides");
+    let returns = take_option(&mut args, "--returns");
+    let purchase_orders = take_option(&mut args, "--purchase-orders");
+    // Order info language, defaults to the system locale
+    let locale = take_option(&mut args, "--lang")
+        .or_else(|| env::var("LANG").ok())
+        .and_then(|tag| Locale::from_tag(&tag))
+        .unwrap_or_default();
+    let mut rules = StalledRules::default();
+    if let Some(days) = take_option(&mut args, "--stalled-days") {
+        rules.in_stock_days = days.parse().map_err(|_| format!("Could not parse the number of days {}", days))?;
+    }
+    if let Some(countries) = take_option(&mut args, "--embargo") {
+        rules.embargoed_countries = countries.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect();
     }
-    let my_order = args[3].parse::<usize>();
-    if my_order.is_err() {
-        println!("Could not parse the order ID you provided {} as usize number. {:?}", args[3], my_order.err());
+    if args.len() < 4 {
+        println!("You need to provide the mouser fulfillment data CSV, production data CSV and an order ID or command as a command line parameters!");
+        println!("Commands: revenue, validate, shipments, stalled [output CSV], anonymize <output CSV>");
+        println!("Optional: --overrides <status override CSV> --returns <returns CSV> --purchase-orders <Mouser purchase order CSV> --lang <en|de> --stalled-days <days> --embargo <country codes>");
         exit(1);
     }
-    let my_order = my_order.unwrap();
 
     let mut orders = glasgow_data::Orders::new(&args[1], &args[2])?;
     orders.calculate_queue();
+    if let Some(overrides) = overrides {
+        orders.load_status_overrides(&overrides)?;
+    }
+    if let Some(returns) = returns {
+        orders.load_returns(&returns)?;
+    }
+    if let Some(purchase_orders) = purchase_orders {
+        orders.load_purchase_orders(&purchase_orders)?;
+    }
 
-    orders.print_stats();
-    println!();
+    match args[3].as_str() {
+        "revenue" => orders.print_revenue(),
+        "validate" => orders.print_validation(),
+        "shipments" => orders.print_shipments(),
+        "stalled" => {
+            orders.print_stalled(&rules);
+            if let Some(output) = args.get(4) {
+                let stalled = orders.stalled_orders(&rules);
+                stalled::write_stalled_csv(&stalled, std::fs::File::create(output)?)?;
+                println!("Wrote {} stalled orders to {}", stalled.len(), output);
+            }
+        }
+        "anonymize" => {
+            let Some(output) = args.get(4) else {
+                println!("The anonymize command needs an output CSV path!");
+                exit(1);
+            };
+            let count = anonymize::anonymize_order_data(&args[1], output)?;
+            println!("Wrote {} anonymized order records to {}", count, output);
+        }
+        order_id => {
+            let my_order = order_id.parse::<usize>();
+            if my_order.is_err() {
+                println!("Could not parse the order ID you provided {} as usize number. {:?}", order_id, my_order.err());
+                exit(1);
+            }
+            let my_order = my_order.unwrap();
 
-    // orders.print_skipped();
-    // println!();
+            orders.print_stats();
+            println!();
 
-    orders.print_order_info(my_order);
+            let report = orders.validate();
+            if !report.is_clean() {
+                println!("Found {} data quality issues, run the validate command for details.", report.diagnostics.len());
+                println!();
+            }
+
+            orders.print_order_info(my_order, locale);
+        }
+    }
 
     Ok(())
 }
@@ -39,4 +104,4 @@ fn main() {
         println!("{}", err);
         process::exit(1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{collections::{BTreeSet, HashMap}, error::Error};
+use chrono::NaiveDateTime;
+use crate::order_data::OrderData;
+
+/// Only keep the day of a timestamp, the time of day is not needed for the queue
+fn day_only(time: &NaiveDateTime) -> String {
+    time.format("%Y-%m-%d 00:00:00").to_string()
+}
+
+/// Write a copy of the order export that is safe to share
+///
+/// Order IDs are replaced with sequential numbers in the same order, as the ID
+/// together with the order date is what backers look their orders up with.
+/// Company names, tracking numbers and email hashes are removed and all times
+/// are cut down to the day. The result still loads as regular order data and
+/// queues the same way, so it can be used for bug reports and test fixtures.
+/// Returns the number of rows written.
+pub fn anonymize_order_data(input: &str, output: &str) -> Result<usize, Box<dyn Error>> {
+    let order_data = OrderData::new(input)?;
+    let surrogates: HashMap<usize, usize> = order_data.records.iter()
+        .map(|r| r.order_id)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .zip(1..)
+        .collect();
+    let mut wtr = csv::WriterBuilder::new().terminator(csv::Terminator::CRLF).from_path(output)?;
+    wtr.write_record(["Order ID", "Part Number", "Product Name", "Qty", "Unit Price", "Subtotal",
+        "Company", "Country Code", "Placed Time", "Shipped Time", "Tracking"])?;
+    for r in &order_data.records {
+        wtr.write_record([
+            surrogates[&r.order_id].to_string(),
+            r.part_number.clone(),
+            r.product_name.clone(),
+            r.qty.to_string(),
+            r.unit_price.amount(),
+            r.subtotal.amount(),
+            String::new(),
+            r.country_code.clone(),
+            day_only(&r.placed_time),
+            r.shipped_time.as_ref().map(day_only).unwrap_or_default(),
+            String::new()
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(order_data.records.len())
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+/// Product we deliver to the distributor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CatalogEntry {
+    /// Our part number, as used in the production data
+    pub vendor_pn: &'static str,
+    /// Part number Mouser lists the product under
+    pub mouser_pn: &'static str,
+    pub name: &'static str
+}
+
+pub const GLASGOW: CatalogEntry = CatalogEntry {
+    vendor_pn: "GLASGOW-C3",
+    mouser_pn: "392-GLASGOW-C3",
+    name: "Glasgow"
+};
+
+pub const GLASGOW_CASE: CatalogEntry = CatalogEntry {
+    vendor_pn: "GLASGOW-C3-AL-CASE",
+    mouser_pn: "392-GLASGOWC3AL-CASE",
+    name: "Glasgow Case"
+};
+
+pub const CATALOG: &[CatalogEntry] = &[GLASGOW, GLASGOW_CASE];
+
+/// Look up a catalog entry by our vendor part number
+pub fn find_vendor_pn(vendor_pn: &str) -> Option<&'static CatalogEntry> {
+    CATALOG.iter().find(|e| e.vendor_pn == vendor_pn)
+}
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use serde::{Serialize, Deserialize};
+use crate::glasgow_data::{Orders, OrderStatus};
+
+/// Difference of one order between two loads of the data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderChange {
+    pub order_id: usize,
+    pub old_status: OrderStatus,
+    pub new_status: OrderStatus,
+    pub old_orders_ahead: usize,
+    pub new_orders_ahead: usize
+}
+
+impl OrderChange {
+    pub fn status_changed(&self) -> bool {
+        self.old_status != self.new_status
+    }
+
+    pub fn queue_moved(&self) -> bool {
+        self.old_orders_ahead != self.new_orders_ahead
+    }
+}
+
+impl fmt::Display for OrderChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.status_changed() {
+            parts.push(format!("Order {} is now {} (was {}).", self.order_id, self.new_status, self.old_status));
+        }
+        if self.queue_moved() && self.new_status.is_open() {
+            parts.push(format!("There are now {} orders to fulfill before it is the turn of order {} (was {}).",
+                self.new_orders_ahead, self.order_id, self.old_orders_ahead));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl Orders {
+    /// Status and queue position changes of all orders compared to an earlier load of the data
+    ///
+    /// Orders that are new or no longer in the data are not reported.
+    pub fn changes_since(&self, previous: &Orders) -> Vec<OrderChange> {
+        self.orders.iter().filter_map(|order| {
+            let old = previous.get_order(order.cs_id)?;
+            let change = OrderChange {
+                order_id: order.cs_id,
+                old_status: old.status,
+                new_status: order.status,
+                old_orders_ahead: previous.orders_ahead(old),
+                new_orders_ahead: self.orders_ahead(order)
+            };
+            // Queue movement of orders that are done is not interesting
+            let moved = change.queue_moved() && change.new_status.is_open();
+            (change.status_changed() || moved).then_some(change)
+        }).collect()
+    }
+}
+
+/// Number with the noun in singular or plural form
+fn counted(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+/// Campaign wide progress between two loads of the data
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CampaignUpdate {
+    pub glasgows_delivered: usize,
+    pub glasgow_cases_delivered: usize,
+    pub orders_shipped: usize,
+    pub orders_partially_shipped: usize
+}
+
+impl CampaignUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.glasgows_delivered == 0 && self.glasgow_cases_delivered == 0
+            && self.orders_shipped == 0 && self.orders_partially_shipped == 0
+    }
+}
+
+impl fmt::Display for CampaignUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.glasgows_delivered > 0 {
+            parts.push(format!("{} delivered to Mouser", counted(self.glasgows_delivered, "Glasgow")));
+        }
+        if self.glasgow_cases_delivered > 0 {
+            parts.push(format!("{} delivered to Mouser", counted(self.glasgow_cases_delivered, "Glasgow Case")));
+        }
+        if self.orders_shipped > 0 {
+            parts.push(format!("{} shipped", counted(self.orders_shipped, "order")));
+        }
+        if self.orders_partially_shipped > 0 {
+            parts.push(format!("{} partially shipped", counted(self.orders_partially_shipped, "order")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+impl Orders {
+    /// New Mouser deliveries and shipments compared to an earlier load of the data
+    pub fn update_since(&self, previous: &Orders) -> CampaignUpdate {
+        let changes = self.changes_since(previous);
+        let became = |status: OrderStatus| changes.iter()
+            .filter(|c| c.status_changed() && c.new_status == status)
+            .count();
+        CampaignUpdate {
+            // Corrections of the production data can lower the counts, those are not news
+            glasgows_delivered: self.glasgow_sent_to_mouser.saturating_sub(previous.glasgow_sent_to_mouser),
+            glasgow_cases_delivered: self.glasgow_cases_sent_to_mouser.saturating_sub(previous.glasgow_cases_sent_to_mouser),
+            orders_shipped: became(OrderStatus::Shipped),
+            orders_partially_shipped: became(OrderStatus::PartiallyShipped)
+        }
+    }
+}
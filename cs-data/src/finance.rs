@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use crate::glasgow_data::{Orders, OrderStatus};
+use crate::money::Money;
+
+/// Units and revenue accumulated for one slice of the orders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevenueBucket {
+    pub units: usize,
+    pub revenue: Money
+}
+
+impl RevenueBucket {
+    fn add(&mut self, unit_price: Money) {
+        self.units += 1;
+        self.revenue += unit_price;
+    }
+}
+
+/// Financial aggregates over all order line items
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevenueReport {
+    pub total: RevenueBucket,
+    pub by_sku: BTreeMap<String, RevenueBucket>,
+    pub early_bird: RevenueBucket,
+    pub regular: RevenueBucket,
+    pub by_country: BTreeMap<String, RevenueBucket>,
+    /// Orders placed with a company name
+    pub business: RevenueBucket,
+    pub individual: RevenueBucket,
+    /// Revenue of line items that have shipped
+    pub recognized: RevenueBucket,
+    /// Revenue of line items still waiting to ship
+    pub deferred: RevenueBucket,
+    /// Cancelled and refunded orders, not included in any of the other buckets
+    pub refunded: RevenueBucket
+}
+
+impl Orders {
+    pub fn revenue_report(&self) -> RevenueReport {
+        let mut report = RevenueReport::default();
+        for o in &self.orders {
+            for item in &o.items {
+                if matches!(o.status, OrderStatus::Cancelled | OrderStatus::Refunded) {
+                    report.refunded.add(item.unit_price);
+                    continue;
+                }
+                report.total.add(item.unit_price);
+                report.by_sku.entry(item.sku.clone()).or_default().add(item.unit_price);
+                if item.product.is_early_bird() {
+                    report.early_bird.add(item.unit_price);
+                } else {
+                    report.regular.add(item.unit_price);
+                }
+                report.by_country.entry(o.country.to_uppercase()).or_default().add(item.unit_price);
+                if o.company.is_some() {
+                    report.business.add(item.unit_price);
+                } else {
+                    report.individual.add(item.unit_price);
+                }
+                if item.is_shipped() {
+                    report.recognized.add(item.unit_price);
+                } else {
+                    report.deferred.add(item.unit_price);
+                }
+            }
+        }
+        report
+    }
+
+    pub fn print_revenue(&self) {
+        let report = self.revenue_report();
+        let print_bucket = |name: &str, bucket: &RevenueBucket| {
+            println!("  {:<24} {:>6} units {:>16}", name, bucket.units, bucket.revenue);
+        };
+        println!("Total revenue:");
+        print_bucket("All orders", &report.total);
+        println!("Revenue by SKU:");
+        for (sku, bucket) in &report.by_sku {
+            print_bucket(sku, bucket);
+        }
+        println!("Revenue by tier:");
+        print_bucket("Early Bird", &report.early_bird);
+        print_bucket("Regular", &report.regular);
+        println!("Revenue by customer type:");
+        print_bucket("Business", &report.business);
+        print_bucket("Individual", &report.individual);
+        println!("Revenue recognition:");
+        print_bucket("Recognized (shipped)", &report.recognized);
+        print_bucket("Deferred (unshipped)", &report.deferred);
+        print_bucket("Cancelled/Refunded", &report.refunded);
+        println!("Revenue by country:");
+        for (country, bucket) in &report.by_country {
+            print_bucket(country, bucket);
+        }
+    }
+}
@@ -4,9 +4,19 @@
 
 use core::fmt;
 use std::error::Error;
+use std::collections::BTreeMap;
+use std::str::FromStr;
 use crate::order_data;
 use crate::production_data;
-use chrono::NaiveDate;
+use crate::catalog::{self, CatalogEntry};
+use crate::i18n::Locale;
+use crate::purchase_order_data;
+use crate::returns_data::{self, Disposition};
+use crate::override_data;
+use crate::tracking::Tracking;
+use crate::money::Money;
+use crate::validation::{self, Diagnostic, DiagnosticKind};
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,31 +28,256 @@ pub enum Product {
     Unknown{name: String}
 }
 
+impl Product {
+    pub fn is_glasgow(&self) -> bool {
+        matches!(self, Product::Glasgow{..} | Product::GlasgowEarlyBird{..})
+    }
+
+    pub fn is_glasgow_case(&self) -> bool {
+        matches!(self, Product::GlasgowCase{..} | Product::GlasgowCaseEarlyBird{..})
+    }
+
+    /// Catalog entry the product is delivered to Mouser as, early bird units are the same hardware
+    pub fn catalog_entry(&self) -> Option<&'static CatalogEntry> {
+        if self.is_glasgow() {
+            Some(&catalog::GLASGOW)
+        } else if self.is_glasgow_case() {
+            Some(&catalog::GLASGOW_CASE)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_early_bird(&self) -> bool {
+        matches!(self, Product::GlasgowEarlyBird{..} | Product::GlasgowCaseEarlyBird{..})
+    }
+
+    /// Position of the unit in the product queue, unknown products are not queued
+    pub fn queue_id(&self) -> Option<usize> {
+        match self {
+            Product::Glasgow { id } |
+            Product::GlasgowCase { id } |
+            Product::GlasgowEarlyBird { id } |
+            Product::GlasgowCaseEarlyBird { id } => Some(*id),
+            Product::Unknown { .. } => None
+        }
+    }
+}
+
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Product::Glasgow { .. } => write!(f, "Glasgow"),
+            Product::GlasgowCase { .. } => write!(f, "Glasgow Case"),
+            Product::GlasgowEarlyBird { .. } => write!(f, "EarlyBird Glasgow"),
+            Product::GlasgowCaseEarlyBird { .. } => write!(f, "EarlyBird Glasgow Case"),
+            Product::Unknown { name } => write!(f, "{}", name)
+        }
+    }
+}
+
+/// Fulfillment state of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    /// Waiting for more units to be delivered to the distributor
+    Queued,
+    /// All units of the order are at the distributor and waiting to be shipped
+    ReadyAtDistributor,
+    /// Some of the line items have shipped
+    PartiallyShipped,
+    /// All line items have shipped
+    Shipped,
+    /// Order is held back, for example because of pending fees
+    OnHold,
+    Cancelled,
+    Refunded
+}
+
+impl OrderStatus {
+    pub const ALL: [OrderStatus; 7] = [
+        OrderStatus::Queued,
+        OrderStatus::ReadyAtDistributor,
+        OrderStatus::PartiallyShipped,
+        OrderStatus::Shipped,
+        OrderStatus::OnHold,
+        OrderStatus::Cancelled,
+        OrderStatus::Refunded,
+    ];
+
+    /// The order still needs attention from the distributor
+    pub fn is_open(&self) -> bool {
+        !matches!(self, OrderStatus::Shipped | OrderStatus::Cancelled | OrderStatus::Refunded)
+    }
+
+    /// Status set by an override entry rather than derived from the data
+    pub fn is_manual(&self) -> bool {
+        matches!(self, OrderStatus::OnHold | OrderStatus::Cancelled | OrderStatus::Refunded)
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            OrderStatus::Queued => "Queued",
+            OrderStatus::ReadyAtDistributor => "Ready at distributor",
+            OrderStatus::PartiallyShipped => "Partially shipped",
+            OrderStatus::Shipped => "Shipped",
+            OrderStatus::OnHold => "On hold",
+            OrderStatus::Cancelled => "Cancelled",
+            OrderStatus::Refunded => "Refunded",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "queued" => Ok(OrderStatus::Queued),
+            "ready" | "readyatdistributor" => Ok(OrderStatus::ReadyAtDistributor),
+            "partiallyshipped" => Ok(OrderStatus::PartiallyShipped),
+            "shipped" => Ok(OrderStatus::Shipped),
+            "onhold" | "hold" => Ok(OrderStatus::OnHold),
+            "cancelled" | "canceled" => Ok(OrderStatus::Cancelled),
+            "refunded" => Ok(OrderStatus::Refunded),
+            _ => Err(format!("Unknown order status \"{}\"", s))
+        }
+    }
+}
+
+/// A single unit of a product in an order together with its shipping state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineItem {
+    pub product: Product,
+    pub sku: String,
+    pub unit_price: Money,
+    pub shipped_time: Option<NaiveDateTime>,
+    pub tracking: Option<Tracking>
+}
+
+impl LineItem {
+    /// A tracking number counts as shipped even if the export has no shipped time yet
+    pub fn is_shipped(&self) -> bool {
+        self.shipped_time.is_some() || self.tracking.is_some()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub cs_id: usize,
     pub date: NaiveDate,
     pub queue_id: usize,
-    pub products: Vec<Product>,
+    pub items: Vec<LineItem>,
     pub contains_early_bird: bool,
     pub country: String,
-    pub fulfilled: bool
+    pub company: Option<String>,
+    /// Hash of the backer email address for order verification, never serialized
+    #[serde(skip_serializing, default)]
+    pub email_hash: Option<String>,
+    pub status: OrderStatus,
+    pub status_override: Option<OrderStatus>,
+    pub status_note: Option<String>
+}
+
+impl Order {
+    /// Number of product units in the order that have shipped
+    pub fn shipped_item_count(&self) -> usize {
+        self.items.iter().filter(|i| i.is_shipped()).count()
+    }
+
+    /// Some but not all of the line items have shipped
+    pub fn is_partially_shipped(&self) -> bool {
+        let shipped = self.shipped_item_count();
+        shipped > 0 && shipped < self.items.len()
+    }
+
+    /// Most recent shipment of any line item in the order
+    pub fn last_shipped_time(&self) -> Option<NaiveDateTime> {
+        self.items.iter().filter_map(|i| i.shipped_time).max()
+    }
 }
 
+/// Units of an order that were returned by the backer
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnedUnits {
+    pub order_id: usize,
+    pub sku: String,
+    pub product: Product,
+    pub qty: usize,
+    pub date: NaiveDate,
+    pub disposition: Disposition
+}
+
+/// Units requested by, delivered to and still owed to the distributor for a catalog entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributorRequest {
+    pub vendor_pn: String,
+    pub name: String,
+    pub requested: usize,
+    pub delivered: usize,
+    pub restocked: usize
+}
+
+impl DistributorRequest {
+    /// Units we still have to deliver, negative if we delivered more than requested
+    pub fn owed(&self) -> i64 {
+        self.requested as i64 - self.delivered as i64
+    }
+}
+
+/// Whether the units delivered to the distributor cover a product unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Availability {
+    /// The unit is at the distributor and ready to ship
+    InStock,
+    /// Units we still have to deliver to the distributor before this unit is covered
+    NeedsMoreUnits(usize)
+}
+
+impl Availability {
+    pub fn is_in_stock(&self) -> bool {
+        *self == Availability::InStock
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Orders {
     pub orders: Vec<Order>,
     pub glasgow_count: usize,
     pub glasgow_case_count: usize,
     pub glasgow_sent_to_mouser: usize,
-    pub glasgow_cases_sent_to_mouser: usize
+    pub glasgow_cases_sent_to_mouser: usize,
+    /// Returned units that went back into the Mouser stock
+    pub glasgow_restocked: usize,
+    pub glasgow_cases_restocked: usize,
+    /// Returned units and RMAs
+    pub returns: Vec<ReturnedUnits>,
+    /// Units Mouser requested from us per vendor part number, from the distributor purchase orders
+    pub requested_by_mouser: Option<BTreeMap<String, usize>>,
+    /// Deliveries to Mouser grouped by shipment order number
+    pub shipments: Vec<production_data::Shipment>,
+    /// Data quality problems found while loading the data
+    pub diagnostics: Vec<Diagnostic>,
+    /// Rows read from the order export and the production data
+    pub order_record_count: usize,
+    pub production_record_count: usize,
+    /// Newest order in the export, a hint how current the export is
+    pub newest_placed_time: Option<NaiveDateTime>
 }
 
 impl Orders {
-    pub fn new(order_data: &String, production_data: &String) -> Result<Self, Box<dyn Error>> {
+    pub fn new(order_data: &str, production_data: &str) -> Result<Self, Box<dyn Error>> {
         //println!("Parsing Order Data.");
         let order_data = order_data::OrderData::new(order_data)?;
         let mut orders: Vec<Order> = Vec::new();
+        let mut diagnostics = validation::check_order_records(&order_data.records);
         for record in order_data.records.iter() {
             let mut early_bird = false;
             let product =
@@ -54,43 +289,51 @@ impl Orders {
                     _ => Product::Unknown { name: record.product_name.clone() }
                 };
 
-            let shipped = record.shipped_time.is_some();
-
-            let mut added = false;
-            for order  in orders.iter_mut() {
-                if order.cs_id == record.order_id {
-                    for _ in 0..record.qty {
-                        order.products.push(product.clone());
-                    }
-                    if early_bird {
-                        order.contains_early_bird = true;
-                    }
-                    added = true;
+            let item = LineItem {
+                product,
+                sku: record.part_number.clone(),
+                unit_price: record.unit_price,
+                shipped_time: record.shipped_time,
+                tracking: record.tracking.as_deref().and_then(Tracking::new)
+            };
+
+            // Each CSV row is one line of the order, carrying its own shipping state
+            if let Some(order) = orders.iter_mut().find(|o| o.cs_id == record.order_id) {
+                for _ in 0..record.qty {
+                    order.items.push(item.clone());
                 }
-            }
-
-            if !added {
+                if early_bird {
+                    order.contains_early_bird = true;
+                }
+                order.date = order.date.min(record.placed_time.date());
+            } else {
                 orders.push(Order {
                     cs_id: record.order_id,
                     date: record.placed_time.date(),
                     queue_id: 0,
-                    products: vec![product; record.qty],
+                    items: vec![item; record.qty],
                     contains_early_bird: early_bird,
                     country: record.country_code.clone(),
-                    fulfilled: shipped
+                    company: record.company.clone().filter(|c| !c.trim().is_empty()),
+                    email_hash: record.email_hash.clone().filter(|h| !h.trim().is_empty()),
+                    status: OrderStatus::Queued,
+                    status_override: None,
+                    status_note: None
                 })
             }
         }
 
         //println!("Parsing production data.");
         let production_data = production_data::ProductionData::new(production_data)?;
+        diagnostics.extend(validation::check_production_records(&production_data.records));
+        let shipments = production_data::Shipment::from_records(&production_data.records);
         let mut glasgow_sent_to_mouser = 0_usize;
         let mut glasgow_cases_sent_to_mouser = 0_usize;
         for record in production_data.records.iter() {
-                match record.product_name.as_str() {
-                    "GLASGOW-C3" => glasgow_sent_to_mouser += record.qty,
-                    "GLASGOW-C3-AL-CASE" => glasgow_cases_sent_to_mouser += record.qty,
-                    _ => println!("Unknown Product {}", record.product_name.to_string())
+                match catalog::find_vendor_pn(&record.product_name) {
+                    Some(&catalog::GLASGOW) => glasgow_sent_to_mouser += record.qty,
+                    Some(&catalog::GLASGOW_CASE) => glasgow_cases_sent_to_mouser += record.qty,
+                    _ => continue
                 };
         }
 
@@ -99,17 +342,39 @@ impl Orders {
             glasgow_count: 0,
             glasgow_case_count: 0,
             glasgow_sent_to_mouser,
-            glasgow_cases_sent_to_mouser
+            glasgow_cases_sent_to_mouser,
+            glasgow_restocked: 0,
+            glasgow_cases_restocked: 0,
+            returns: Vec::new(),
+            requested_by_mouser: None,
+            shipments,
+            diagnostics,
+            order_record_count: order_data.records.len(),
+            production_record_count: production_data.records.len(),
+            newest_placed_time: order_data.records.iter().map(|r| r.placed_time).max()
         })
     }
 
+    /// Newest shipped time of any line item
+    pub fn newest_shipped_time(&self) -> Option<NaiveDateTime> {
+        self.orders.iter()
+            .flat_map(|o| o.items.iter())
+            .filter_map(|i| i.shipped_time)
+            .max()
+    }
+
+    /// Date of the newest delivery to Mouser
+    pub fn newest_delivery_date(&self) -> Option<NaiveDate> {
+        self.shipments.iter().map(|s| s.date).max()
+    }
+
     /// Sort orders by Crowd Supply order id in ascending order
-    fn sort(self: &mut Self) {
-        self.orders.sort_by(|a, b| a.cs_id.cmp(&b.cs_id));
+    fn sort(&mut self) {
+        self.orders.sort_by_key(|o| o.cs_id);
     }
 
     /// Calculate queue IDs for a specific order type slice
-    fn calculate_queue_for_slice(self: &mut Self, early_bird: bool, order_counter_start: usize) -> usize {
+    fn calculate_queue_for_slice(&mut self, early_bird: bool, order_counter_start: usize) -> usize {
         let mut order_counter = order_counter_start;
         // Set order queue ID for the slice we are processing
         for o in self.orders.iter_mut().filter(|o| o.contains_early_bird == early_bird) {
@@ -118,7 +383,7 @@ impl Orders {
         }
         // Increment product queue IDs depending on the slice we are processing
         for o in &mut self.orders {
-            for p in &mut o.products {
+            for p in o.items.iter_mut().map(|i| &mut i.product) {
                 if early_bird {
                     match p {
                         Product::GlasgowEarlyBird { id } => {
@@ -149,105 +414,301 @@ impl Orders {
         order_counter
     }
 
-    pub fn calculate_queue(self: &mut Self) {
+    pub fn calculate_queue(&mut self) {
         self.sort();
 
         // Assign queue ids to orders and products
         let queue_id = self.calculate_queue_for_slice(true, 0);
         self.calculate_queue_for_slice(false, queue_id);
+
+        self.calculate_status();
+    }
+
+    /// Load manual status overrides (on hold, cancelled, refunded...) from a CSV file
+    pub fn load_status_overrides(&mut self, override_data: &str) -> Result<(), Box<dyn Error>> {
+        let override_data = override_data::OverrideData::new(override_data)?;
+        for record in override_data.records.iter() {
+            let status = record.status.parse::<OrderStatus>()?;
+            match self.orders.iter_mut().find(|o| o.cs_id == record.order_id) {
+                Some(order) => {
+                    order.status_override = Some(status);
+                    order.status_note = record.note.clone().filter(|n| !n.trim().is_empty());
+                },
+                None => self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::UnknownOverride,
+                    order_id: Some(record.order_id),
+                    message: format!("Status override {} for an order ID that is not in the export", status)
+                })
+            }
+        }
+        self.calculate_status();
+        Ok(())
     }
 
-    pub fn get_order_count(self: &Self) -> usize {
+    /// Load returned units from a CSV file, restocked units count towards the Mouser inventory
+    pub fn load_returns(&mut self, returns_data: &str) -> Result<(), Box<dyn Error>> {
+        let returns_data = returns_data::ReturnsData::new(returns_data)?;
+        for record in returns_data.records.iter() {
+            let disposition = record.disposition.parse::<Disposition>()?;
+            let order = self.orders.iter().find(|o| o.cs_id == record.order_id);
+            let items: Vec<&LineItem> = order
+                .map(|o| o.items.iter().filter(|i| i.sku == record.sku).collect())
+                .unwrap_or_default();
+            let Some(item) = items.first() else {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::InvalidReturn,
+                    order_id: Some(record.order_id),
+                    message: format!("Return of {} x {} that is not part of the order", record.qty, record.sku)
+                });
+                continue;
+            };
+            let shipped = items.iter().filter(|i| i.is_shipped()).count();
+            if record.qty > shipped {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::InvalidReturn,
+                    order_id: Some(record.order_id),
+                    message: format!("Return of {} x {} but only {} shipped", record.qty, record.sku, shipped)
+                });
+            }
+            self.returns.push(ReturnedUnits {
+                order_id: record.order_id,
+                sku: record.sku.clone(),
+                product: item.product.clone(),
+                qty: record.qty,
+                date: record.date,
+                disposition
+            });
+        }
+
+        let restocked = |filter: fn(&Product) -> bool| -> usize {
+            self.returns
+                .iter()
+                .filter(|r| r.disposition == Disposition::Restocked && filter(&r.product))
+                .map(|r| r.qty)
+                .sum()
+        };
+        self.glasgow_restocked = restocked(Product::is_glasgow);
+        self.glasgow_cases_restocked = restocked(Product::is_glasgow_case);
+
+        self.calculate_status();
+        Ok(())
+    }
+
+    /// Units that ended up at Mouser, delivered by us or restocked from returns
+    pub fn get_glasgow_supply_count(&self) -> usize {
+        self.glasgow_sent_to_mouser + self.glasgow_restocked
+    }
+
+    pub fn get_glasgow_cases_supply_count(&self) -> usize {
+        self.glasgow_cases_sent_to_mouser + self.glasgow_cases_restocked
+    }
+
+    /// Load the distributor purchase orders, replacing the requested counts derived from the orders
+    pub fn load_purchase_orders(&mut self, purchase_order_data: &str) -> Result<(), Box<dyn Error>> {
+        let purchase_order_data = purchase_order_data::PurchaseOrderData::new(purchase_order_data)?;
+        let mut requested: BTreeMap<String, usize> = BTreeMap::new();
+        for record in purchase_order_data.records.iter() {
+            if catalog::find_vendor_pn(&record.product_name).is_none() {
+                self.diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::UnknownPurchaseOrderPart,
+                    order_id: None,
+                    message: format!("{} units of {} requested on {} (PO No {})", record.qty, record.product_name, record.date, record.po_no)
+                });
+                continue;
+            }
+            *requested.entry(record.product_name.clone()).or_default() += record.qty;
+        }
+        self.requested_by_mouser = Some(requested);
+        Ok(())
+    }
+
+    /// Units ordered by backers, delivered and restocked for a catalog entry
+    fn catalog_counts(&self, entry: &CatalogEntry) -> (usize, usize, usize) {
+        match *entry {
+            catalog::GLASGOW => (self.glasgow_count, self.glasgow_sent_to_mouser, self.glasgow_restocked),
+            catalog::GLASGOW_CASE => (self.glasgow_case_count, self.glasgow_cases_sent_to_mouser, self.glasgow_cases_restocked),
+            _ => (0, 0, 0)
+        }
+    }
+
+    /// Per catalog entry request status, falls back to the backer order totals without purchase orders
+    pub fn distributor_requests(&self) -> Vec<DistributorRequest> {
+        catalog::CATALOG
+            .iter()
+            .map(|entry| {
+                let (ordered, delivered, restocked) = self.catalog_counts(entry);
+                let requested = match &self.requested_by_mouser {
+                    Some(requested) => requested.get(entry.vendor_pn).copied().unwrap_or_default(),
+                    None => ordered
+                };
+                DistributorRequest {
+                    vendor_pn: entry.vendor_pn.to_string(),
+                    name: entry.name.to_string(),
+                    requested,
+                    delivered,
+                    restocked
+                }
+            })
+            .collect()
+    }
+
+    /// Check if a product unit is covered by the units delivered to the distributor
+    ///
+    /// Queue IDs are 0-based, so unit `id` is covered once `id + 1` units arrived.
+    /// Products we can not queue do not hold an order back and count as in stock.
+    pub fn product_availability(&self, product: &Product) -> Availability {
+        let (id, supply) = match product {
+            Product::Glasgow { id } | Product::GlasgowEarlyBird { id } => (*id, self.get_glasgow_supply_count()),
+            Product::GlasgowCase { id } | Product::GlasgowCaseEarlyBird { id } => (*id, self.get_glasgow_cases_supply_count()),
+            Product::Unknown { .. } => return Availability::InStock
+        };
+        if id < supply {
+            Availability::InStock
+        } else {
+            Availability::NeedsMoreUnits(id + 1 - supply)
+        }
+    }
+
+    /// Derive the status of an order from its line items, overrides and the distributor inventory
+    fn order_status(&self, order: &Order) -> OrderStatus {
+        if let Some(status) = order.status_override {
+            return status;
+        }
+        let shipped = order.items.iter().filter(|i| i.is_shipped()).count();
+        if !order.items.is_empty() && shipped == order.items.len() {
+            OrderStatus::Shipped
+        } else if shipped > 0 {
+            OrderStatus::PartiallyShipped
+        } else if order.items.iter().all(|i| self.product_availability(&i.product).is_in_stock()) {
+            OrderStatus::ReadyAtDistributor
+        } else {
+            OrderStatus::Queued
+        }
+    }
+
+    /// Update the status of all orders, needs to be rerun when the queue or overrides change
+    fn calculate_status(&mut self) {
+        let statuses: Vec<OrderStatus> = self.orders.iter().map(|o| self.order_status(o)).collect();
+        for (order, status) in self.orders.iter_mut().zip(statuses) {
+            order.status = status;
+        }
+    }
+
+    pub fn get_order_count(&self) -> usize {
         self.orders.len()
     }
 
-    pub fn get_fulfilled_count(self: &Self) -> usize {
-        self.orders.iter().filter(|o| o.fulfilled).count()
+    pub fn get_status_count(&self, status: OrderStatus) -> usize {
+        self.orders.iter().filter(|o| o.status == status).count()
+    }
+
+    pub fn get_fulfilled_count(&self) -> usize {
+        self.get_status_count(OrderStatus::Shipped)
+    }
+
+    /// Number of orders that still have to ship before it is the turn of this order
+    pub fn orders_ahead(&self, order: &Order) -> usize {
+        order.queue_id.saturating_sub(self.get_fulfilled_count())
     }
 
-    pub fn get_fulfilled_percent(self: &Self) -> f64 {
+    pub fn get_fulfilled_percent(&self) -> f64 {
         ((self.get_fulfilled_count() as f64) / self.get_order_count() as f64) * 100.0
     }
 
-    pub fn get_remaining_count(self: &Self) -> usize {
-        self.orders.iter().filter(|o| !o.fulfilled).count()
+    pub fn get_remaining_count(&self) -> usize {
+        self.orders.iter().filter(|o| o.status.is_open()).count()
     }
 
-    pub fn get_remaining_percent(self: &Self) -> f64 {
+    pub fn get_remaining_percent(&self) -> f64 {
         ((self.get_remaining_count() as f64) / self.get_order_count() as f64) * 100.0
     }
 
-    pub fn get_fulfilled_glasgow_count(self: &Self) -> usize {
-        self.orders.iter().map(|o| if o.fulfilled {
-            o.products
-                .iter()
-                .filter(|p|
-                    matches!(p, Product::Glasgow{..}) ||
-                    matches!(p, Product::GlasgowEarlyBird{..}
-                    ))
-                .count()
-        } else { 0 }).sum()
+    pub fn get_partially_shipped_count(&self) -> usize {
+        self.get_status_count(OrderStatus::PartiallyShipped)
+    }
+
+    /// Count shipped units per line item, so partially shipped orders are accounted for
+    fn get_shipped_product_count(&self, filter: impl Fn(&Product) -> bool) -> usize {
+        self.orders
+            .iter()
+            .flat_map(|o| o.items.iter())
+            .filter(|i| i.is_shipped() && filter(&i.product))
+            .count()
+    }
+
+    pub fn get_fulfilled_glasgow_count(&self) -> usize {
+        self.get_shipped_product_count(Product::is_glasgow)
     }
 
-    pub fn get_fulfilled_glasgow_percent(self: &Self) -> f64 {
+    pub fn get_fulfilled_glasgow_percent(&self) -> f64 {
         ((self.get_fulfilled_glasgow_count() as f64) / self.glasgow_count as f64) * 100.0
     }
 
-    pub fn get_glasgow_at_mouser_count(self: &Self) -> i32 {
-        self.glasgow_sent_to_mouser as i32 - self.get_fulfilled_glasgow_count() as i32
+    pub fn get_glasgow_at_mouser_count(&self) -> i32 {
+        self.get_glasgow_supply_count() as i32 - self.get_fulfilled_glasgow_count() as i32
     }
 
-    pub fn get_glasgow_at_mouser_percent(self: &Self) -> f64 {
+    pub fn get_glasgow_at_mouser_percent(&self) -> f64 {
         (self.get_glasgow_at_mouser_count() as f64 / self.glasgow_count as f64) * 100.0
     }
 
-    pub fn get_remaining_glasgow_count(self: &Self) -> i32 {
-        self.glasgow_count as i32 - self.glasgow_sent_to_mouser as i32
+    pub fn get_remaining_glasgow_count(&self) -> i32 {
+        self.glasgow_count as i32 - self.get_glasgow_supply_count() as i32
     }
 
-    pub fn get_remaining_glasgow_percent(self: &Self) -> f64 {
+    pub fn get_remaining_glasgow_percent(&self) -> f64 {
         (self.get_remaining_glasgow_count() as f64 / self.glasgow_count as f64) * 100.0
     }
 
-    pub fn get_fulfilled_glasgow_cases_count(self: &Self) -> usize {
-        self.orders.iter().map(|o| if o.fulfilled {
-            o.products
-                .iter()
-                .filter(|p|
-                    matches!(p, Product::GlasgowCase{..}) ||
-                    matches!(p, Product::GlasgowCaseEarlyBird{..}
-                    ))
-                .count()
-        } else { 0 }).sum()
+    pub fn get_fulfilled_glasgow_cases_count(&self) -> usize {
+        self.get_shipped_product_count(Product::is_glasgow_case)
     }
 
-    pub fn get_fulfilled_glasgow_cases_percent(self: &Self) -> f64 {
+    pub fn get_fulfilled_glasgow_cases_percent(&self) -> f64 {
         ((self.get_fulfilled_glasgow_cases_count() as f64) / self.glasgow_case_count as f64) * 100.0
     }
 
-    pub fn get_glasgow_cases_at_mouser_count(self: &Self) -> i32 {
-        self.glasgow_cases_sent_to_mouser as i32 - self.get_fulfilled_glasgow_cases_count() as i32
+    pub fn get_glasgow_cases_at_mouser_count(&self) -> i32 {
+        self.get_glasgow_cases_supply_count() as i32 - self.get_fulfilled_glasgow_cases_count() as i32
     }
 
-    pub fn get_glasgow_cases_at_mouser_percent(self: &Self) -> f64 {
+    pub fn get_glasgow_cases_at_mouser_percent(&self) -> f64 {
         (((self.get_glasgow_cases_at_mouser_count()) as f64) / self.glasgow_case_count as f64) * 100.0
     }
 
-    pub fn get_remaining_glasgow_cases_count(self: &Self) -> i32 {
-        self.glasgow_case_count as i32 - self.glasgow_cases_sent_to_mouser as i32
+    pub fn get_remaining_glasgow_cases_count(&self) -> i32 {
+        self.glasgow_case_count as i32 - self.get_glasgow_cases_supply_count() as i32
     }
 
-    pub fn get_remaining_glasgow_cases_percent(self: &Self) -> f64 {
+    pub fn get_remaining_glasgow_cases_percent(&self) -> f64 {
         (self.get_remaining_glasgow_cases_count() as f64 / self.glasgow_case_count as f64) * 100.0
     }
 
-    pub fn print_stats(self: &Self) {
-        println!("We sent {} Glasgows and {} Glasgow Cases to Mouser.", self.glasgow_sent_to_mouser, self.glasgow_cases_sent_to_mouser);
+    pub fn print_stats(&self) {
+        for request in self.distributor_requests() {
+            println!("Mouser requested {} {} units, we sent {}, still owed {}.", request.requested, request.name, request.delivered, request.owed());
+        }
+        if !self.returns.is_empty() {
+            println!("Backers returned {} units, {} Glasgows and {} Glasgow Cases went back into the Mouser stock.",
+                self.returns.iter().map(|r| r.qty).sum::<usize>(),
+                self.glasgow_restocked,
+                self.glasgow_cases_restocked
+            );
+        }
         println!("We received {} orders, out of which {} ({:.1}%) are fulfilled.",
             self.get_order_count(),
             self.get_fulfilled_count(),
             self.get_fulfilled_percent(),
         );
+        println!("Order status: {} queued, {} ready at Mouser, {} partially shipped, {} shipped, {} on hold, {} cancelled, {} refunded.",
+            self.get_status_count(OrderStatus::Queued),
+            self.get_status_count(OrderStatus::ReadyAtDistributor),
+            self.get_status_count(OrderStatus::PartiallyShipped),
+            self.get_status_count(OrderStatus::Shipped),
+            self.get_status_count(OrderStatus::OnHold),
+            self.get_status_count(OrderStatus::Cancelled),
+            self.get_status_count(OrderStatus::Refunded),
+        );
         println!("The orders contain {} Glasgows, out of which {} ({:.1}%) are at Mouser and {} ({:.1}%) have shipped.",
             self.glasgow_count,
             self.get_glasgow_at_mouser_count(),
@@ -264,35 +725,30 @@ impl Orders {
         );
     }
 
-    pub fn print_skipped(self: &Self) {
-        println!("Here is a list of orders that have all items with queue IDs lower than the number of supplied items, but have not shipped:");
-        let mut skipped_order_count = 0;
-        for o in &self.orders {
-            if !o.fulfilled {
-                let mut can_fulfill = true;
-                for p in &o.products {
-                    match p {
-                        Product::Glasgow { id } => if id > &self.glasgow_sent_to_mouser { can_fulfill = false },
-                        Product::GlasgowCase { id } => if id > &self.glasgow_cases_sent_to_mouser { can_fulfill = false },
-                        Product::GlasgowEarlyBird { id } => if id > &self.glasgow_sent_to_mouser { can_fulfill = false },
-                        Product::GlasgowCaseEarlyBird { id } => if id > &self.glasgow_cases_sent_to_mouser { can_fulfill = false },
-                        Product::Unknown { name: _ } => continue,
-                    }
-                }
-                if can_fulfill {
-                    println!("Order ID {}, ordered from {}", o.cs_id, o.country);
-                    skipped_order_count += 1;
-                }
+    pub fn print_shipments(&self) {
+        println!("Shipments to Mouser:");
+        for shipment in &self.shipments {
+            let units: Vec<String> = shipment.units()
+                .iter()
+                .map(|(pn, qty)| format!("{} x {}", qty, pn))
+                .collect();
+            println!("{} Order No {}: {} boxes, {}",
+                shipment.date,
+                shipment.order_no,
+                shipment.box_count(),
+                units.join(", ")
+            );
+            for note in shipment.notes() {
+                println!("  Note: {}", note);
             }
         }
-        println!("Skipped order count: {}", skipped_order_count);
     }
 
-    pub fn get_order(self: &Self, order_id: usize) -> Option<&Order> {
+    pub fn get_order(&self, order_id: usize) -> Option<&Order> {
         self.orders.iter().find(|o| o.cs_id == order_id)
     }
 
-    pub fn print_order_info(self: &Self, order_id: usize) {
+    pub fn print_order_info(&self, order_id: usize, locale: Locale) {
         let order = self.get_order(order_id);
 
         // Order not found
@@ -303,62 +759,63 @@ impl Orders {
 
         let order = order.unwrap();
 
-        println!("Your order number {} was placed on {} and has the queue id {}.", order_id, order.date, order.queue_id);
-
-        // Order fulfilled
-        if order.fulfilled {
-            println!("Your order was fulfilled.");
-            return;
+        println!("{}", locale.tr3("order.placed", order_id, locale.date(order.date), order.queue_id));
+        println!("{}", locale.tr1("order.status", locale.status(&order.status)));
+
+        match order.status {
+            OrderStatus::Shipped => {
+                println!("{}", locale.tr2("order.fulfilled", order_id, order.queue_id));
+                return;
+            },
+            OrderStatus::Cancelled | OrderStatus::Refunded => return,
+            OrderStatus::OnHold => {
+                println!("{}", locale.tr("order.on_hold_plain"));
+                if let Some(note) = &order.status_note {
+                    println!("{}", locale.tr1("order.note", note));
+                }
+            },
+            _ => ()
         }
 
         // Found order, print info
-        println!("We have fulfilled {} orders, so there are still {} orders to fulfill before it is your turn.",
-            self.get_fulfilled_count(),
-            order.queue_id as i64 - self.get_fulfilled_count() as i64
-        );
+        println!("{}", locale.tr2("order.fulfilled_so_far",
+            locale.int(self.get_fulfilled_count()),
+            locale.int(order.queue_id as i64 - self.get_fulfilled_count() as i64)
+        ));
         if (order.queue_id as i64 - self.get_fulfilled_count() as i64) < 0 {
-            println!("According to our records your order should have already shipped, there might be a lot of reasons why this is the case.");
-            println!("Here are a few potential reasons:");
-            println!(" * You are located in a country that got sanctioned after the campaign happened, for example Russian Federation.");
-            println!(" * You had or have pending fees on your order due to customs cost adjustments and your queue position had to be pushed.");
-            println!(" * Our queue placement prediction does not match reality. You can contact Crowd Supply directly and ask for the reason why your order was not shipped yet.");
+            println!("{}", locale.tr("order.overdue"));
+            println!("{}", locale.tr("order.overdue_reasons"));
+            println!(" * {}", locale.tr("order.overdue_sanctions"));
+            println!(" * {}", locale.tr("order.overdue_fees"));
+            println!(" * {} {}", locale.tr("order.overdue_prediction"), locale.tr("order.overdue_contact_plain"));
         }
-        println!("Your order contains:");
-        for p in &order.products {
-            match p {
-                Product::Glasgow { id } => {
-                    print!("- Glasgow with the queue ID {}, ", id);
-                    if id <= &self.glasgow_sent_to_mouser {
-                        println!("it is at Mouser and will ship soon, if all items in your order are available.")
-                    } else {
-                        println!("we have to ship {} Glasgows to Mouser before your order can be fulfilled.", id - self.glasgow_sent_to_mouser)
-                    }
-                },
-                Product::GlasgowCase { id } => {
-                    print!("- Glasgow Case with the queue ID {}, ", id);
-                    if id <= &self.glasgow_cases_sent_to_mouser {
-                        println!("it is at Mouser and will ship soon, if all items in your order are available.")
-                    } else {
-                        println!("we have to ship {} cases to Mouser before your order can be fulfilled.", id - self.glasgow_cases_sent_to_mouser)
-                    }
-                },
-                Product::GlasgowEarlyBird { id } => {
-                    print!("- EarlyBird Glasgow with the queue ID {}, ", id);
-                    if id <= &self.glasgow_sent_to_mouser {
-                        println!("it is at Mouser and will ship soon, if all items in your order are available.")
-                    } else {
-                        println!("we have to ship {} Glasgows to Mouser before your order can be fulfilled.", id - self.glasgow_sent_to_mouser)
-                    }
-                },
-                Product::GlasgowCaseEarlyBird { id } => {
-                    print!("- EarlyBird Glasgow Case with the queue ID {}, ", id);
-                    if id <= &self.glasgow_cases_sent_to_mouser {
-                        println!("it is at Mouser and will ship soon, if all items in your order are available.")
-                    } else {
-                        println!("we have to ship {} cases to Mouser before your order can be fulfilled.", id - self.glasgow_cases_sent_to_mouser)
-                    }
-                },
-                Product::Unknown { name } => println!("- Unknown Product with the name \"{}\".", name),
+        if order.is_partially_shipped() {
+            println!("{}", locale.tr2("order.partially_shipped", order.shipped_item_count(), order.items.len()));
+        }
+        println!("{}", locale.tr("order.contains"));
+        for item in &order.items {
+            let Some(id) = item.product.queue_id() else {
+                println!("{}", locale.tr1("order.item_unknown", &item.product));
+                continue;
+            };
+            print!("{}, ", locale.tr2("order.item", &item.product, id));
+            if item.is_shipped() {
+                match item.shipped_time {
+                    Some(time) => print!("{}", locale.tr1("order.item_shipped_on", locale.date(time.date()))),
+                    None => print!("{}", locale.tr("order.item_shipped")),
+                }
+                match &item.tracking {
+                    Some(tracking) => match &tracking.url {
+                        Some(url) => println!(" {}", locale.tr3("shipped.tracking_url", tracking.carrier, &tracking.number, url)),
+                        None => println!(" {}.", locale.tr1("shipped.tracking_number", &tracking.number)),
+                    },
+                    None => println!("."),
+                }
+                continue;
+            }
+            match self.product_availability(&item.product) {
+                Availability::InStock => println!("{}", locale.tr("order.item_at_mouser")),
+                Availability::NeedsMoreUnits(units) => println!("{}", locale.tr1("order.item_needs_units", locale.int(units)))
             }
         }
     }
@@ -368,4 +825,66 @@ impl fmt::Display for Orders {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "order data")
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orders_with_supply(glasgows: usize, glasgow_cases: usize) -> Orders {
+        Orders {
+            glasgow_sent_to_mouser: glasgows,
+            glasgow_cases_sent_to_mouser: glasgow_cases,
+            ..Orders::default()
+        }
+    }
+
+    #[test]
+    fn nothing_delivered() {
+        let orders = orders_with_supply(0, 0);
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 0 }), Availability::NeedsMoreUnits(1));
+        assert_eq!(orders.product_availability(&Product::GlasgowCase { id: 0 }), Availability::NeedsMoreUnits(1));
+    }
+
+    #[test]
+    fn last_delivered_unit_is_in_stock() {
+        let orders = orders_with_supply(150, 172);
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 149 }), Availability::InStock);
+        assert_eq!(orders.product_availability(&Product::GlasgowEarlyBird { id: 149 }), Availability::InStock);
+        assert_eq!(orders.product_availability(&Product::GlasgowCase { id: 171 }), Availability::InStock);
+        assert_eq!(orders.product_availability(&Product::GlasgowCaseEarlyBird { id: 171 }), Availability::InStock);
+    }
+
+    #[test]
+    fn first_undelivered_unit_needs_one_more() {
+        let orders = orders_with_supply(150, 172);
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 150 }), Availability::NeedsMoreUnits(1));
+        assert_eq!(orders.product_availability(&Product::GlasgowEarlyBird { id: 151 }), Availability::NeedsMoreUnits(2));
+        assert_eq!(orders.product_availability(&Product::GlasgowCase { id: 172 }), Availability::NeedsMoreUnits(1));
+        assert_eq!(orders.product_availability(&Product::GlasgowCaseEarlyBird { id: 200 }), Availability::NeedsMoreUnits(29));
+    }
+
+    #[test]
+    fn products_use_their_own_supply() {
+        let orders = orders_with_supply(10, 0);
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 5 }), Availability::InStock);
+        assert_eq!(orders.product_availability(&Product::GlasgowCase { id: 5 }), Availability::NeedsMoreUnits(6));
+    }
+
+    #[test]
+    fn restocked_units_count_as_supply() {
+        let orders = Orders {
+            glasgow_restocked: 1,
+            ..orders_with_supply(10, 0)
+        };
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 10 }), Availability::InStock);
+        assert_eq!(orders.product_availability(&Product::Glasgow { id: 11 }), Availability::NeedsMoreUnits(1));
+    }
+
+    #[test]
+    fn unknown_products_do_not_hold_orders_back() {
+        let orders = orders_with_supply(0, 0);
+        let product = Product::Unknown { name: "Glasgow Sticker".to_string() };
+        assert_eq!(orders.product_availability(&product), Availability::InStock);
+    }
+}
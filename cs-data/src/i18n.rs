@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use std::{collections::HashMap, sync::OnceLock};
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::glasgow_data::OrderStatus;
+
+/// Language of the backer facing texts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    En,
+    De
+}
+
+/// Message catalogs, one `key = text` per line, `{0}` style placeholders for arguments
+const EN_MESSAGES: &str = include_str!("../locales/en.txt");
+const DE_MESSAGES: &str = include_str!("../locales/de.txt");
+
+type Catalog = HashMap<&'static str, &'static str>;
+
+fn parse_catalog(source: &'static str) -> Catalog {
+    source.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, text)| (key.trim(), text.trim()))
+        .collect()
+}
+
+fn catalog(locale: Locale) -> &'static Catalog {
+    static EN: OnceLock<Catalog> = OnceLock::new();
+    static DE: OnceLock<Catalog> = OnceLock::new();
+    match locale {
+        Locale::En => EN.get_or_init(|| parse_catalog(EN_MESSAGES)),
+        Locale::De => DE.get_or_init(|| parse_catalog(DE_MESSAGES)),
+    }
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::En, Locale::De];
+
+    /// Language tag as used in HTML and HTTP headers
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de"
+        }
+    }
+
+    /// Name of the language in the language itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::De => "Deutsch"
+        }
+    }
+
+    /// Match a language tag like "de", "de-AT" or a POSIX locale like "de_DE.UTF-8"
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_', '.']).next()?.to_lowercase();
+        Locale::ALL.iter().copied().find(|l| l.code() == language)
+    }
+
+    /// Pick the supported language the client prefers most from an Accept-Language header
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, Locale)> = accept_language.split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = Locale::from_tag(parts.next()?)?;
+                let quality = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+        // Stable sort keeps the header order for equal weights
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, locale)| *locale)
+    }
+
+    /// Translated message, missing translations fall back to English and then to the key
+    pub fn tr<'a>(&self, key: &'a str) -> &'a str {
+        catalog(*self).get(key)
+            .or_else(|| catalog(Locale::En).get(key))
+            .copied()
+            .unwrap_or(key)
+    }
+
+    fn format(&self, key: &str, args: &[&dyn fmt::Display]) -> String {
+        args.iter().enumerate().fold(self.tr(key).to_string(), |text, (i, arg)| {
+            text.replace(&format!("{{{}}}", i), &arg.to_string())
+        })
+    }
+
+    pub fn tr1(&self, key: &str, a: impl fmt::Display) -> String {
+        self.format(key, &[&a])
+    }
+
+    pub fn tr2(&self, key: &str, a: impl fmt::Display, b: impl fmt::Display) -> String {
+        self.format(key, &[&a, &b])
+    }
+
+    pub fn tr3(&self, key: &str, a: impl fmt::Display, b: impl fmt::Display, c: impl fmt::Display) -> String {
+        self.format(key, &[&a, &b, &c])
+    }
+
+    pub fn status(&self, status: &OrderStatus) -> &'static str {
+        self.tr(match status {
+            OrderStatus::Queued => "status.queued",
+            OrderStatus::ReadyAtDistributor => "status.ready",
+            OrderStatus::PartiallyShipped => "status.partially_shipped",
+            OrderStatus::Shipped => "status.shipped",
+            OrderStatus::OnHold => "status.on_hold",
+            OrderStatus::Cancelled => "status.cancelled",
+            OrderStatus::Refunded => "status.refunded",
+        })
+    }
+
+    pub fn date(&self, date: NaiveDate) -> String {
+        match self {
+            Locale::En => date.format("%Y-%m-%d").to_string(),
+            Locale::De => date.format("%d.%m.%Y").to_string()
+        }
+    }
+
+    fn separators(&self) -> (char, char) {
+        match self {
+            Locale::En => (',', '.'),
+            Locale::De => ('.', ',')
+        }
+    }
+
+    /// Whole number with thousands separators
+    pub fn int(&self, n: impl fmt::Display) -> String {
+        let digits = n.to_string();
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(rest) => ("-", rest.to_string()),
+            None => ("", digits)
+        };
+        let (thousands, _) = self.separators();
+        let mut grouped = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(thousands);
+            }
+            grouped.push(c);
+        }
+        format!("{}{}", sign, grouped)
+    }
+
+    /// Number with one decimal place, as used for percentages
+    pub fn decimal(&self, n: f64) -> String {
+        let text = format!("{:.1}", n);
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, "0"));
+        let (_, decimal) = self.separators();
+        format!("{}{}{}", self.int(whole), decimal, fraction)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_tag() {
+        assert_eq!(Locale::from_tag("en"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("de"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("DE"), Some(Locale::De));
+        assert_eq!(Locale::from_tag(" en-US "), Some(Locale::En));
+        assert_eq!(Locale::from_tag("de_DE.UTF-8"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("fr"), None);
+        assert_eq!(Locale::from_tag("deu"), None);
+        assert_eq!(Locale::from_tag(""), None);
+    }
+
+    #[test]
+    fn negotiate() {
+        assert_eq!(Locale::negotiate("de-DE,de;q=0.9,en;q=0.5"), Some(Locale::De));
+        assert_eq!(Locale::negotiate("en;q=0.5, de;q=0.8"), Some(Locale::De));
+        assert_eq!(Locale::negotiate("fr-FR,fr;q=0.9,en;q=0.3,de;q=0.2"), Some(Locale::En));
+        // Equal weights keep the header order
+        assert_eq!(Locale::negotiate("en,de"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("de;q=0.7,en;q=0.7"), Some(Locale::De));
+        // q=0 means not acceptable, broken weights count as 1
+        assert_eq!(Locale::negotiate("de;q=0,en;q=0.1"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("en;q=0.5,de;q=x"), Some(Locale::De));
+        assert_eq!(Locale::negotiate("fr,*;q=0.5"), None);
+        assert_eq!(Locale::negotiate(""), None);
+    }
+
+    #[test]
+    fn int() {
+        assert_eq!(Locale::En.int(0), "0");
+        assert_eq!(Locale::En.int(999), "999");
+        assert_eq!(Locale::En.int(1000), "1,000");
+        assert_eq!(Locale::En.int(1234567), "1,234,567");
+        assert_eq!(Locale::De.int(1234567), "1.234.567");
+        assert_eq!(Locale::En.int(-5), "-5");
+        assert_eq!(Locale::En.int(-123456), "-123,456");
+        assert_eq!(Locale::De.int(-1000), "-1.000");
+    }
+
+    #[test]
+    fn decimal() {
+        assert_eq!(Locale::En.decimal(0.0), "0.0");
+        assert_eq!(Locale::En.decimal(42.25), "42.2");
+        assert_eq!(Locale::De.decimal(42.26), "42,3");
+        assert_eq!(Locale::En.decimal(1234.5), "1,234.5");
+        assert_eq!(Locale::De.decimal(1234.5), "1.234,5");
+        assert_eq!(Locale::En.decimal(-12.5), "-12.5");
+        assert_eq!(Locale::De.decimal(-1234.56), "-1.234,6");
+    }
+}
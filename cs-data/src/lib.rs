@@ -4,4 +4,17 @@
 
 pub mod order_data;
 pub mod glasgow_data;
-pub mod production_data;
\ No newline at end of file
+pub mod production_data;
+pub mod override_data;
+pub mod tracking;
+pub mod finance;
+pub mod money;
+pub mod validation;
+pub mod catalog;
+pub mod returns_data;
+pub mod purchase_order_data;
+pub mod anonymize;
+pub mod changes;
+pub mod timeline;
+pub mod i18n;
+pub mod stalled;
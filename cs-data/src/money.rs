@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+/// Currency of a money amount, the Crowd Supply export is always in USD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Currency {
+    #[default]
+    Usd
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Currency::Usd => write!(f, "USD")
+        }
+    }
+}
+
+/// Exact money amount stored as a whole number of cents
+///
+/// Adding up thousands of prices as floats drifts away from the real total,
+/// integer cents always add up exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub cents: i64,
+    pub currency: Currency
+}
+
+impl Money {
+    pub fn from_cents(cents: i64) -> Self {
+        Self {
+            cents,
+            currency: Currency::Usd
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self::from_cents(0)
+    }
+
+    /// Price of qty units, None when the total does not fit
+    pub fn checked_mul(self, qty: usize) -> Option<Money> {
+        let cents = self.cents.checked_mul(i64::try_from(qty).ok()?)?;
+        Some(Money {
+            cents,
+            currency: self.currency
+        })
+    }
+
+    /// Plain decimal amount without the currency, as used in the CSV exports
+    pub fn amount(&self) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let cents = self.cents.unsigned_abs();
+        format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Honor width and alignment so amounts line up in tables
+        f.pad(&format!("{} {}", self.amount(), self.currency))
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    /// Parse a decimal amount like "145.00" without going through floating point
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim();
+        let s = input.trim_start_matches('$');
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s)
+        };
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let fraction = fraction.trim_end_matches('0');
+        if whole.is_empty() && fraction.is_empty()
+            || !whole.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid money amount \"{}\"", input));
+        }
+        if fraction.len() > 2 {
+            return Err(format!("Money amount \"{}\" has fractions of a cent", input));
+        }
+        let out_of_range = || format!("Money amount \"{}\" is out of range", input);
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| out_of_range())? };
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| out_of_range())?;
+        let cents = whole.checked_mul(100).and_then(|c| c.checked_add(fraction)).ok_or_else(out_of_range)?;
+        Ok(Self::from_cents(if negative { -cents } else { cents }))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        debug_assert_eq!(self.currency, rhs.currency);
+        Money {
+            cents: self.cents + rhs.cents,
+            currency: self.currency
+        }
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), |acc, m| acc + m)
+    }
+}
+
+/// Serde helper for money columns in the CSV exports
+pub(crate) mod money_deserializer {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use super::Money;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        let amount: String = Deserialize::deserialize(deserializer)?;
+        amount.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_amounts() {
+        let cases = [
+            ("145.00", 14500),
+            ("145", 14500),
+            ("145.5", 14550),
+            ("145.50", 14550),
+            ("145.500", 14550),
+            (".99", 99),
+            ("0.01", 1),
+            ("$55.00", 5500),
+            (" 5.00 ", 500),
+            ("-12.34", -1234),
+            ("$-0.50", -50),
+        ];
+        for (input, cents) in cases {
+            assert_eq!(input.parse::<Money>(), Ok(Money::from_cents(cents)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("".parse::<Money>(), Err("Invalid money amount \"\"".to_string()));
+        assert_eq!("-".parse::<Money>(), Err("Invalid money amount \"-\"".to_string()));
+        assert_eq!("12,50".parse::<Money>(), Err("Invalid money amount \"12,50\"".to_string()));
+        assert_eq!("1.2.3".parse::<Money>(), Err("Invalid money amount \"1.2.3\"".to_string()));
+        assert_eq!("EUR 5.00".parse::<Money>(), Err("Invalid money amount \"EUR 5.00\"".to_string()));
+        assert_eq!("-0.125".parse::<Money>(), Err("Money amount \"-0.125\" has fractions of a cent".to_string()));
+        assert_eq!("92233720368547758.08".parse::<Money>(), Err("Money amount \"92233720368547758.08\" is out of range".to_string()));
+        assert_eq!("-99999999999999999999".parse::<Money>(), Err("Money amount \"-99999999999999999999\" is out of range".to_string()));
+        assert_eq!("92233720368547758.07".parse::<Money>(), Ok(Money::from_cents(i64::MAX)));
+    }
+
+    #[test]
+    fn format_with_currency() {
+        assert_eq!(Money::from_cents(14500).to_string(), "145.00 USD");
+        assert_eq!(Money::from_cents(-5).to_string(), "-0.05 USD");
+        assert_eq!(format!("{:>12}", Money::from_cents(99)), "    0.99 USD");
+        assert_eq!(Money::from_cents(-1234).amount(), "-12.34");
+    }
+
+    #[test]
+    fn add_amounts() {
+        assert_eq!(Money::from_cents(14500) + Money::from_cents(5500), Money::from_cents(20000));
+        assert_eq!(Money::from_cents(100) + Money::from_cents(-250), Money::from_cents(-150));
+        let mut total = Money::zero();
+        total += Money::from_cents(1);
+        total += Money::from_cents(2);
+        assert_eq!(total, Money::from_cents(3));
+        // 0.1 + 0.2 is exactly 0.3 in cents
+        let sum: Money = ["0.10", "0.20"].iter().map(|a| a.parse::<Money>().unwrap()).sum();
+        assert_eq!(sum, "0.30".parse().unwrap());
+    }
+
+    #[test]
+    fn multiply_amounts() {
+        assert_eq!(Money::from_cents(14500).checked_mul(3), Some(Money::from_cents(43500)));
+        assert_eq!(Money::from_cents(14500).checked_mul(0), Some(Money::zero()));
+        assert_eq!(Money::from_cents(-50).checked_mul(2), Some(Money::from_cents(-100)));
+        assert_eq!(Money::from_cents(i64::MAX).checked_mul(2), None);
+        assert_eq!(Money::from_cents(1).checked_mul(usize::MAX), None);
+    }
+}
@@ -5,6 +5,7 @@
 use std::error::Error;
 use serde::Deserialize;
 use chrono::NaiveDateTime;
+use crate::money::{money_deserializer, Money};
 
 mod date_deserializer {
     use serde::{de::Error, Deserializer};
@@ -12,7 +13,7 @@ mod date_deserializer {
 
     pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
         let time: String = Deserialize::deserialize(deserializer)?;
-        Ok(NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M:%S%.f").map_err(D::Error::custom)?)
+        NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M:%S%.f").map_err(D::Error::custom)
     }
 
     pub fn opt_deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
@@ -28,7 +29,6 @@ mod date_deserializer {
 
 /// Crowd Supply order data record
 #[derive(Debug, Deserialize)]
-#[allow(unused)]
 pub(crate) struct Record {
     #[serde(rename = "Order ID")]
     pub order_id: usize,
@@ -38,10 +38,10 @@ pub(crate) struct Record {
     pub product_name: String,
     #[serde(rename = "Qty")]
     pub qty: usize,
-    #[serde(rename = "Unit Price")]
-    pub unit_price: f32,
-    #[serde(rename = "Subtotal")]
-    pub subtotal: f32,
+    #[serde(rename = "Unit Price", deserialize_with = "money_deserializer::deserialize")]
+    pub unit_price: Money,
+    #[serde(rename = "Subtotal", deserialize_with = "money_deserializer::deserialize")]
+    pub subtotal: Money,
     #[serde(rename = "Company")]
     pub company: Option<String>,
     #[serde(rename = "Country Code")]
@@ -51,7 +51,10 @@ pub(crate) struct Record {
     #[serde(rename = "Shipped Time", deserialize_with = "date_deserializer::opt_deserialize")]
     pub shipped_time: Option<NaiveDateTime>,
     #[serde(rename = "Tracking")]
-    pub tracking: Option<String>
+    pub tracking: Option<String>,
+    /// Optional hex SHA-256 of the backer email address, not part of every export
+    #[serde(rename = "Email Hash", default)]
+    pub email_hash: Option<String>
 }
 
 /// Deserialized Crowd Supply order data
@@ -60,8 +63,8 @@ pub(crate) struct OrderData {
 }
 
 impl OrderData {
-    pub fn new(data: &String) -> Result<Self, Box<dyn Error>> {
-        let mut rdr = csv::Reader::from_path(data).unwrap();
+    pub fn new(data: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_path(data)?;
         let records = rdr.deserialize().collect::<Result<Vec<Record>, csv::Error>>()?;
         Ok(Self {
             records
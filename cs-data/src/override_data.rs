@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::error::Error;
+use serde::Deserialize;
+
+/// Manually maintained order status override record
+#[derive(Debug, Deserialize)]
+pub struct Record {
+    #[serde(rename = "Order ID")]
+    pub order_id: usize,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Note")]
+    pub note: Option<String>
+}
+
+/// Deserialized order status override data
+pub struct OverrideData {
+    pub records: Vec<Record>
+}
+
+impl OverrideData {
+    pub fn new(data: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_path(data)?;
+        let records = rdr.deserialize().collect::<Result<Vec<Record>, csv::Error>>()?;
+        Ok(Self {
+            records
+        })
+    }
+}
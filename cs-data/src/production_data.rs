@@ -2,8 +2,9 @@
 // SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
 // SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
 
+use std::collections::BTreeMap;
 use std::error::Error;
-use serde::Deserialize;
+use serde::{Serialize, Deserialize};
 use chrono::NaiveDate;
 
 mod date_deserializer {
@@ -12,14 +13,13 @@ mod date_deserializer {
 
     pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
         let time: String = Deserialize::deserialize(deserializer)?;
-        Ok(NaiveDate::parse_from_str(&time, "%m/%d/%Y").map_err(D::Error::custom)?)
+        NaiveDate::parse_from_str(&time, "%m/%d/%Y").map_err(D::Error::custom)
     }
 
 }
 
-/// Crowd Supply order data record
+/// Production data record, one box delivered to Mouser
 #[derive(Debug, Deserialize)]
-#[allow(unused)]
 pub struct Record {
     #[serde(rename = "Date", deserialize_with = "date_deserializer::deserialize")]
     pub date: NaiveDate,
@@ -33,19 +33,92 @@ pub struct Record {
     pub product_name: String,
     #[serde(rename = "QTY")]
     pub qty: usize,
+    #[serde(rename = "Note", default)]
+    pub note: Option<String>,
 }
 
-/// Deserialized Crowd Supply order data
+/// Deserialized production data
 pub struct ProductionData {
     pub records: Vec<Record>
 }
 
 impl ProductionData {
-    pub fn new(data: &String) -> Result<Self, Box<dyn Error>> {
-        let mut rdr = csv::Reader::from_path(data).unwrap();
+    pub fn new(data: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_path(data)?;
         let records = rdr.deserialize().collect::<Result<Vec<Record>, csv::Error>>()?;
         Ok(Self {
             records
         })
     }
-}
\ No newline at end of file
+}
+
+/// Box of units delivered to Mouser as part of a shipment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductionBox {
+    pub box_no: usize,
+    pub mouser_pn: String,
+    pub vendor_pn: String,
+    pub qty: usize,
+    pub note: Option<String>
+}
+
+/// All boxes delivered to Mouser under one order number
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shipment {
+    pub order_no: String,
+    pub date: NaiveDate,
+    pub boxes: Vec<ProductionBox>
+}
+
+impl Shipment {
+    /// Group production records into shipments by order number, sorted by date
+    pub fn from_records(records: &[Record]) -> Vec<Shipment> {
+        let mut shipments: Vec<Shipment> = Vec::new();
+        for record in records {
+            let production_box = ProductionBox {
+                box_no: record.box_no,
+                mouser_pn: record.part_number.clone(),
+                vendor_pn: record.product_name.clone(),
+                qty: record.qty,
+                note: record.note.clone().filter(|n| !n.trim().is_empty())
+            };
+            match shipments.iter_mut().find(|s| s.order_no == record.order_no) {
+                Some(shipment) => {
+                    shipment.date = shipment.date.min(record.date);
+                    shipment.boxes.push(production_box);
+                },
+                None => shipments.push(Shipment {
+                    order_no: record.order_no.clone(),
+                    date: record.date,
+                    boxes: vec![production_box]
+                })
+            }
+        }
+        shipments.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.order_no.cmp(&b.order_no)));
+        shipments
+    }
+
+    /// Units in the shipment per vendor part number
+    pub fn units(&self) -> BTreeMap<String, usize> {
+        let mut units: BTreeMap<String, usize> = BTreeMap::new();
+        for b in &self.boxes {
+            *units.entry(b.vendor_pn.clone()).or_default() += b.qty;
+        }
+        units
+    }
+
+    /// Number of distinct boxes, a box can hold more than one part number
+    pub fn box_count(&self) -> usize {
+        let mut boxes: Vec<usize> = self.boxes.iter().map(|b| b.box_no).collect();
+        boxes.sort_unstable();
+        boxes.dedup();
+        boxes.len()
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        self.boxes
+            .iter()
+            .filter_map(|b| b.note.as_ref().map(|n| format!("Box {}: {}", b.box_no, n)))
+            .collect()
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use std::error::Error;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDate;
+
+mod date_deserializer {
+    use serde::{de::Error, Deserializer};
+    use super::*;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let time: String = Deserialize::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(&time, "%Y-%m-%d").map_err(D::Error::custom)
+    }
+
+}
+
+/// What happened to a returned unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Disposition {
+    /// Unit went back into the distributor stock and can ship to another backer
+    Restocked,
+    /// Unit was sent back to us for repair or analysis
+    ReturnedToVendor,
+    Scrapped
+}
+
+impl fmt::Display for Disposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Disposition::Restocked => "Restocked",
+            Disposition::ReturnedToVendor => "Returned to vendor",
+            Disposition::Scrapped => "Scrapped",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Disposition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "restocked" | "restock" => Ok(Disposition::Restocked),
+            "returnedtovendor" | "rtv" => Ok(Disposition::ReturnedToVendor),
+            "scrapped" | "scrap" => Ok(Disposition::Scrapped),
+            _ => Err(format!("Unknown return disposition \"{}\"", s))
+        }
+    }
+}
+
+/// Returned units / RMA record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    #[serde(rename = "Order ID")]
+    pub order_id: usize,
+    #[serde(rename = "SKU")]
+    pub sku: String,
+    #[serde(rename = "Qty")]
+    pub qty: usize,
+    #[serde(rename = "Date", deserialize_with = "date_deserializer::deserialize")]
+    pub date: NaiveDate,
+    #[serde(rename = "Disposition")]
+    pub disposition: String
+}
+
+/// Deserialized returns data
+pub struct ReturnsData {
+    pub records: Vec<Record>
+}
+
+impl ReturnsData {
+    pub fn new(data: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = csv::Reader::from_path(data)?;
+        let records = rdr.deserialize().collect::<Result<Vec<Record>, csv::Error>>()?;
+        Ok(Self {
+            records
+        })
+    }
+}
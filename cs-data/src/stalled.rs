@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use std::{collections::BTreeMap, error::Error, io};
+use chrono::{NaiveDate, Utc};
+use serde::{Serialize, Deserialize};
+use crate::catalog::{self, CatalogEntry};
+use crate::glasgow_data::{Order, Orders};
+use crate::returns_data::Disposition;
+
+/// Thresholds for flagging open orders that should have shipped by now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StalledRules {
+    /// Days all units of an order may sit at the distributor before it counts as stalled
+    pub in_stock_days: i64,
+    /// Country codes the distributor can not ship to, compared case insensitive
+    pub embargoed_countries: Vec<String>,
+    /// Day the overdue days are counted up to
+    pub as_of: NaiveDate
+}
+
+impl Default for StalledRules {
+    fn default() -> Self {
+        Self {
+            in_stock_days: 14,
+            embargoed_countries: Vec::new(),
+            as_of: Utc::now().date_naive()
+        }
+    }
+}
+
+impl StalledRules {
+    pub fn is_embargoed(&self, country: &str) -> bool {
+        self.embargoed_countries.iter().any(|c| c.eq_ignore_ascii_case(country))
+    }
+}
+
+/// Rule that flagged an order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StallReason {
+    /// The order ships to a country on the embargo list, the backer needs to be contacted
+    Embargo { country: String },
+    /// A unit further back in the queue shipped while this order is still waiting
+    Overtaken { by_order: usize, shipped: NaiveDate },
+    /// All units have been at the distributor for longer than the rules allow
+    InStock { since: NaiveDate }
+}
+
+impl StallReason {
+    /// Short machine readable name, used as the CSV reason column
+    pub fn code(&self) -> &'static str {
+        match self {
+            StallReason::Embargo { .. } => "embargo",
+            StallReason::Overtaken { .. } => "overtaken",
+            StallReason::InStock { .. } => "in_stock"
+        }
+    }
+}
+
+impl fmt::Display for StallReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StallReason::Embargo { country } => write!(f, "Ships to {}, which is on the embargo list", country),
+            StallReason::Overtaken { by_order, shipped } => write!(f, "Order ID {} is later in the queue and shipped on {}", by_order, shipped),
+            StallReason::InStock { since } => write!(f, "All units at Mouser since {}", since)
+        }
+    }
+}
+
+/// Open order flagged by one of the stalled order rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StalledOrder {
+    pub order: Order,
+    pub reason: StallReason,
+    /// Days past the point the order should have shipped
+    pub days_overdue: i64
+}
+
+/// Days units of a catalog entry arrived at the distributor, one entry per delivered or restocked unit
+fn stock_dates(orders: &Orders, entry: &CatalogEntry) -> Vec<NaiveDate> {
+    let mut arrivals: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for shipment in &orders.shipments {
+        let delivered: usize = shipment.boxes.iter().filter(|b| b.vendor_pn == entry.vendor_pn).map(|b| b.qty).sum();
+        *arrivals.entry(shipment.date).or_default() += delivered;
+    }
+    for r in orders.returns.iter().filter(|r| r.disposition == Disposition::Restocked && r.product.catalog_entry() == Some(entry)) {
+        *arrivals.entry(r.date).or_default() += r.qty;
+    }
+    arrivals.into_iter().flat_map(|(date, qty)| std::iter::repeat_n(date, qty)).collect()
+}
+
+/// Shipped units of a catalog entry by queue ID, with the earliest shipment at or after each position
+struct ShippedUnits {
+    /// (queue ID, earliest (shipped day, order ID) among this and all later queue IDs)
+    units: Vec<(usize, (NaiveDate, usize))>
+}
+
+impl ShippedUnits {
+    fn new(orders: &Orders, entry: &CatalogEntry) -> Self {
+        let mut units: Vec<(usize, (NaiveDate, usize))> = orders.orders.iter()
+            .flat_map(|o| o.items.iter().map(move |i| (o.cs_id, i)))
+            .filter(|(_, i)| i.product.catalog_entry() == Some(entry))
+            .filter_map(|(cs_id, i)| Some((i.product.queue_id()?, (i.shipped_time?.date(), cs_id))))
+            .collect();
+        units.sort_unstable();
+        for n in (1..units.len()).rev() {
+            units[n - 1].1 = units[n - 1].1.min(units[n].1);
+        }
+        Self { units }
+    }
+
+    /// Earliest shipment of a unit further back in the queue than the given queue ID
+    fn overtaken_by(&self, id: usize) -> Option<(NaiveDate, usize)> {
+        let start = self.units.partition_point(|(unit_id, _)| *unit_id <= id);
+        self.units.get(start).map(|(_, first)| *first)
+    }
+}
+
+impl Orders {
+    /// Open orders that should have shipped according to the rules, in queue order
+    ///
+    /// Orders on hold are left out, someone already looks into them. Each order is
+    /// reported once, for the first matching rule out of embargo, overtaken and in stock.
+    /// Embargoed and overtaken orders are only reported once all their units are at the
+    /// distributor, before that the order is waiting on stock and not on anyone.
+    pub fn stalled_orders(&self, rules: &StalledRules) -> Vec<StalledOrder> {
+        let stock: Vec<(&CatalogEntry, Vec<NaiveDate>, ShippedUnits)> = catalog::CATALOG.iter()
+            .map(|entry| (entry, stock_dates(self, entry), ShippedUnits::new(self, entry)))
+            .collect();
+        let for_entry = |entry: Option<&CatalogEntry>| stock.iter().find(|(e, _, _)| Some(*e) == entry);
+
+        let mut stalled: Vec<StalledOrder> = self.orders.iter()
+            .filter(|o| o.status.is_open() && !o.status.is_manual())
+            .filter_map(|order| {
+                let waiting: Vec<_> = order.items.iter().filter(|i| !i.is_shipped()).collect();
+                if waiting.is_empty() {
+                    return None;
+                }
+                // Day the last waiting unit became available, units we can not queue do not hold the order back
+                let in_stock_since = waiting.iter()
+                    .all(|i| self.product_availability(&i.product).is_in_stock())
+                    .then(|| waiting.iter()
+                        .filter_map(|i| for_entry(i.product.catalog_entry())?.1.get(i.product.queue_id()?).copied())
+                        .fold(order.date, NaiveDate::max));
+                let overtaken = waiting.iter()
+                    .filter_map(|i| Some((i.product.queue_id()?, for_entry(i.product.catalog_entry())?)))
+                    .filter_map(|(id, (_, _, shipped))| shipped.overtaken_by(id))
+                    .min();
+
+                let (reason, days_overdue) = if let (Some(since), true) = (in_stock_since, rules.is_embargoed(&order.country)) {
+                    (StallReason::Embargo { country: order.country.clone() }, (rules.as_of - since).num_days())
+                } else if let (Some(_), Some((shipped, by_order))) = (in_stock_since, overtaken) {
+                    (StallReason::Overtaken { by_order, shipped }, (rules.as_of - shipped).num_days())
+                } else {
+                    let since = in_stock_since?;
+                    let days_overdue = (rules.as_of - since).num_days() - rules.in_stock_days;
+                    if days_overdue <= 0 {
+                        return None;
+                    }
+                    (StallReason::InStock { since }, days_overdue)
+                };
+                Some(StalledOrder { order: order.clone(), reason, days_overdue: days_overdue.max(0) })
+            })
+            .collect();
+        stalled.sort_by_key(|s| s.order.queue_id);
+        stalled
+    }
+
+    pub fn print_stalled(&self, rules: &StalledRules) {
+        println!("Open orders that should have shipped by {} ({} days in stock, embargo list: {}):",
+            rules.as_of,
+            rules.in_stock_days,
+            if rules.embargoed_countries.is_empty() { "none".to_string() } else { rules.embargoed_countries.join(", ") }
+        );
+        let stalled = self.stalled_orders(rules);
+        for s in &stalled {
+            println!("Order ID {}, ordered from {}, {} days overdue: {}", s.order.cs_id, s.order.country, s.days_overdue, s.reason);
+        }
+        println!("Stalled order count: {}", stalled.len());
+    }
+}
+
+/// Write stalled orders as CSV in the form Crowd Supply support asks for
+pub fn write_stalled_csv(stalled: &[StalledOrder], writer: impl io::Write) -> Result<(), Box<dyn Error>> {
+    let mut wtr = csv::WriterBuilder::new().terminator(csv::Terminator::CRLF).from_writer(writer);
+    wtr.write_record(["Order ID", "Queue ID", "Order Date", "Country Code", "Status", "Items",
+        "Reason", "Details", "Days Overdue"])?;
+    for s in stalled {
+        let items: Vec<String> = s.order.items.iter()
+            .filter(|i| !i.is_shipped())
+            .map(|i| i.sku.clone())
+            .collect();
+        wtr.write_record([
+            s.order.cs_id.to_string(),
+            s.order.queue_id.to_string(),
+            s.order.date.to_string(),
+            s.order.country.clone(),
+            s.order.status.to_string(),
+            items.join(" "),
+            s.reason.code().to_string(),
+            s.reason.to_string(),
+            s.days_overdue.to_string()
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::catalog::{self, CatalogEntry};
+use crate::glasgow_data::Orders;
+
+/// Cumulative unit counts up to and including a day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelinePoint {
+    pub date: NaiveDate,
+    pub placed: usize,
+    pub shipped: usize,
+    pub delivered: usize
+}
+
+/// Progress of one catalog product over time, one point per day with activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductTimeline {
+    pub vendor_pn: String,
+    pub name: String,
+    pub points: Vec<TimelinePoint>
+}
+
+/// Per day increments, summed up into the cumulative points
+#[derive(Default)]
+struct DayCounts {
+    placed: usize,
+    shipped: usize,
+    delivered: usize
+}
+
+impl Orders {
+    /// Units ordered by backers, shipped to backers and delivered to Mouser over time
+    pub fn timeline(&self, entry: &CatalogEntry) -> ProductTimeline {
+        let mut days: BTreeMap<NaiveDate, DayCounts> = BTreeMap::new();
+        for order in &self.orders {
+            for item in order.items.iter().filter(|i| i.product.catalog_entry() == Some(entry)) {
+                days.entry(order.date).or_default().placed += 1;
+                if let Some(shipped_time) = item.shipped_time {
+                    days.entry(shipped_time.date()).or_default().shipped += 1;
+                }
+            }
+        }
+        for shipment in &self.shipments {
+            let delivered: usize = shipment.boxes.iter().filter(|b| b.vendor_pn == entry.vendor_pn).map(|b| b.qty).sum();
+            if delivered > 0 {
+                days.entry(shipment.date).or_default().delivered += delivered;
+            }
+        }
+        let mut total = TimelinePoint::default();
+        let points = days.into_iter().map(|(date, counts)| {
+            total.date = date;
+            total.placed += counts.placed;
+            total.shipped += counts.shipped;
+            total.delivered += counts.delivered;
+            total
+        }).collect();
+        ProductTimeline {
+            vendor_pn: entry.vendor_pn.to_string(),
+            name: entry.name.to_string(),
+            points
+        }
+    }
+
+    /// Timelines of all catalog products
+    pub fn timelines(&self) -> Vec<ProductTimeline> {
+        catalog::CATALOG.iter().map(|entry| self.timeline(entry)).collect()
+    }
+}
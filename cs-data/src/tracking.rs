@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use serde::{Serialize, Deserialize};
+
+/// Shipping carrier, detected from the tracking number format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Carrier {
+    Usps,
+    Ups,
+    Dhl,
+    FedEx,
+    Unknown
+}
+
+impl Carrier {
+    /// Guess the carrier from the shape of the tracking number
+    pub fn detect(number: &str) -> Self {
+        let len = number.len();
+        let all_digits = number.chars().all(|c| c.is_ascii_digit());
+        let bytes = number.as_bytes();
+        if len == 18 && number.starts_with("1Z") && number.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Carrier::Ups
+        } else if all_digits && (20..=22).contains(&len) && number.starts_with('9') {
+            // USPS domestic (IMpb) tracking numbers
+            Carrier::Usps
+        } else if len == 13
+            && bytes[..2].iter().all(u8::is_ascii_uppercase)
+            && bytes[2..11].iter().all(u8::is_ascii_digit)
+            && number.ends_with("US") {
+            // UPU S10 international number issued by USPS
+            Carrier::Usps
+        } else if all_digits && (len == 12 || len == 15) {
+            Carrier::FedEx
+        } else if (all_digits && len == 10) || number.starts_with("JJD") || number.starts_with("JVGL") {
+            Carrier::Dhl
+        } else {
+            Carrier::Unknown
+        }
+    }
+
+    /// Link to the carrier tracking page for a tracking number
+    ///
+    /// Numbers with anything but ASCII letters and digits get no link, they would need escaping in the query.
+    pub fn tracking_url(&self, number: &str) -> Option<String> {
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        match self {
+            Carrier::Usps => Some(format!("https://tools.usps.com/go/TrackConfirmAction?tLabels={}", number)),
+            Carrier::Ups => Some(format!("https://www.ups.com/track?tracknum={}", number)),
+            Carrier::Dhl => Some(format!("https://www.dhl.com/global-en/home/tracking/tracking-express.html?submit=1&tracking-id={}", number)),
+            Carrier::FedEx => Some(format!("https://www.fedex.com/fedextrack/?trknbr={}", number)),
+            Carrier::Unknown => None
+        }
+    }
+}
+
+impl fmt::Display for Carrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Carrier::Usps => "USPS",
+            Carrier::Ups => "UPS",
+            Carrier::Dhl => "DHL",
+            Carrier::FedEx => "FedEx",
+            Carrier::Unknown => "Unknown carrier",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Shipment tracking number of a line item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tracking {
+    pub number: String,
+    pub carrier: Carrier,
+    pub url: Option<String>
+}
+
+impl Tracking {
+    /// Parse a tracking number from the export, empty fields have no tracking
+    pub fn new(number: &str) -> Option<Self> {
+        let number: String = number.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+        if number.is_empty() {
+            return None;
+        }
+        let carrier = Carrier::detect(&number);
+        let url = carrier.tracking_url(&number);
+        Some(Self {
+            number,
+            carrier,
+            url
+        })
+    }
+}
+
+impl fmt::Display for Tracking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.number, self.carrier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_carrier() {
+        let cases = [
+            ("1Z999AA10123456784", Carrier::Ups),
+            ("1Z999AA1012345678", Carrier::Unknown),
+            ("1Z999AA1012345678#", Carrier::Unknown),
+            ("92055901755477000271990", Carrier::Unknown),
+            ("9205590175547700027199", Carrier::Usps),
+            ("920559017554770002719", Carrier::Usps),
+            ("92055901755477000271", Carrier::Usps),
+            ("82055901755477000271", Carrier::Unknown),
+            ("EC123456789US", Carrier::Usps),
+            ("EC123456789DE", Carrier::Unknown),
+            ("E1123456789US", Carrier::Unknown),
+            ("123456789012", Carrier::FedEx),
+            ("123456789012345", Carrier::FedEx),
+            ("1234567890123", Carrier::Unknown),
+            ("1234567890", Carrier::Dhl),
+            ("JJD0099999999", Carrier::Dhl),
+            ("JVGL0999999999", Carrier::Dhl),
+            ("123456789", Carrier::Unknown),
+        ];
+        for (number, carrier) in cases {
+            assert_eq!(Carrier::detect(number), carrier, "{}", number);
+        }
+    }
+
+    #[test]
+    fn tracking_url_only_for_plain_numbers() {
+        assert_eq!(Carrier::Ups.tracking_url("1Z999AA10123456784").as_deref(), Some("https://www.ups.com/track?tracknum=1Z999AA10123456784"));
+        assert_eq!(Carrier::Dhl.tracking_url("JJD00&X=<"), None);
+        assert_eq!(Carrier::Dhl.tracking_url(""), None);
+        assert_eq!(Carrier::Unknown.tracking_url("123456789"), None);
+    }
+
+    #[test]
+    fn tracking_normalizes_number() {
+        let tracking = Tracking::new(" 1z999aa1 0123456784 ").unwrap();
+        assert_eq!(tracking.number, "1Z999AA10123456784");
+        assert_eq!(tracking.carrier, Carrier::Ups);
+        assert!(Tracking::new("  ").is_none());
+        let tracking = Tracking::new("JJD00\"><b>").unwrap();
+        assert_eq!(tracking.carrier, Carrier::Dhl);
+        assert_eq!(tracking.url, None);
+    }
+}
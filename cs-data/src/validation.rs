@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use core::fmt;
+use std::collections::{BTreeMap, HashSet};
+use serde::{Serialize, Deserialize};
+use crate::glasgow_data::{Orders, Product};
+use crate::order_data;
+use crate::production_data;
+use crate::catalog;
+
+/// Category of a data quality problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DiagnosticKind {
+    /// Qty times unit price does not add up to the row subtotal
+    PriceMismatch,
+    /// The same order row appears more than once in the export
+    DuplicateRow,
+    /// Order row has a shipped time before the placed time
+    ShippedBeforePlaced,
+    /// Order contains a product we do not know how to queue
+    UnknownProduct,
+    /// Production row for a vendor part number we do not know
+    UnknownProductionPart,
+    /// Purchase order row for a vendor part number we do not know
+    UnknownPurchaseOrderPart,
+    /// Production row Mouser part number does not match the catalog
+    PartNumberMismatch,
+    /// More units shipped to backers than were delivered to the distributor
+    ShippedExceedsDelivered,
+    /// Return entry that does not match what was shipped in the order
+    InvalidReturn,
+    /// Status override for an order ID that is not in the export
+    UnknownOverride
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DiagnosticKind::PriceMismatch => "Price mismatch",
+            DiagnosticKind::DuplicateRow => "Duplicate row",
+            DiagnosticKind::ShippedBeforePlaced => "Shipped before placed",
+            DiagnosticKind::UnknownProduct => "Unknown product",
+            DiagnosticKind::UnknownProductionPart => "Unknown production part",
+            DiagnosticKind::UnknownPurchaseOrderPart => "Unknown purchase order part",
+            DiagnosticKind::PartNumberMismatch => "Part number mismatch",
+            DiagnosticKind::ShippedExceedsDelivered => "Shipped exceeds delivered",
+            DiagnosticKind::InvalidReturn => "Invalid return",
+            DiagnosticKind::UnknownOverride => "Unknown override",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Data quality problem found in the input data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub order_id: Option<usize>,
+    pub message: String
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.order_id {
+            Some(order_id) => write!(f, "{}: Order ID {}: {}", self.kind, order_id, self.message),
+            None => write!(f, "{}: {}", self.kind, self.message)
+        }
+    }
+}
+
+/// Categorized result of a validation pass over the order and production data
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>
+}
+
+impl ValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn by_kind(&self) -> BTreeMap<DiagnosticKind, Vec<&Diagnostic>> {
+        let mut kinds: BTreeMap<DiagnosticKind, Vec<&Diagnostic>> = BTreeMap::new();
+        for d in &self.diagnostics {
+            kinds.entry(d.kind).or_default().push(d);
+        }
+        kinds
+    }
+}
+
+/// Row level checks of the Crowd Supply order export
+pub(crate) fn check_order_records(records: &[order_data::Record]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut seen = HashSet::new();
+    for record in records {
+        match record.unit_price.checked_mul(record.qty) {
+            Some(expected) if expected == record.subtotal => (),
+            Some(expected) => diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::PriceMismatch,
+                order_id: Some(record.order_id),
+                message: format!("{} x {} at {} should be {}, but the subtotal is {}",
+                    record.qty, record.part_number, record.unit_price, expected, record.subtotal)
+            }),
+            None => diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::PriceMismatch,
+                order_id: Some(record.order_id),
+                message: format!("{} x {} at {} is out of range, the subtotal is {}",
+                    record.qty, record.part_number, record.unit_price, record.subtotal)
+            })
+        }
+        let key = (record.order_id, &record.part_number, record.qty, record.placed_time, record.shipped_time);
+        if !seen.insert(key) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::DuplicateRow,
+                order_id: Some(record.order_id),
+                message: format!("{} x {} is listed more than once", record.qty, record.part_number)
+            });
+        }
+        if let Some(shipped_time) = record.shipped_time {
+            if shipped_time < record.placed_time {
+                diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::ShippedBeforePlaced,
+                    order_id: Some(record.order_id),
+                    message: format!("{} was placed {} but shipped {}", record.part_number, record.placed_time, shipped_time)
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Row level checks of the production data
+pub(crate) fn check_production_records(records: &[production_data::Record]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for r in records {
+        match catalog::find_vendor_pn(&r.product_name) {
+            None => diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::UnknownProductionPart,
+                order_id: None,
+                message: format!("{} units of {} delivered on {} (Order No {}, Box {})", r.qty, r.product_name, r.date, r.order_no, r.box_no)
+            }),
+            Some(entry) if entry.mouser_pn != r.part_number => diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::PartNumberMismatch,
+                order_id: None,
+                message: format!("{} is listed as Mouser PN {}, expected {} (Order No {}, Box {})", r.product_name, r.part_number, entry.mouser_pn, r.order_no, r.box_no)
+            }),
+            Some(_) => ()
+        }
+    }
+    diagnostics
+}
+
+impl Orders {
+    /// Check the order and production data against each other
+    pub fn validate(&self) -> ValidationReport {
+        let mut diagnostics = self.diagnostics.clone();
+
+        for o in &self.orders {
+            for item in &o.items {
+                if let Product::Unknown { name } = &item.product {
+                    diagnostics.push(Diagnostic {
+                        kind: DiagnosticKind::UnknownProduct,
+                        order_id: Some(o.cs_id),
+                        message: format!("\"{}\" ({})", name, item.sku)
+                    });
+                }
+            }
+        }
+
+        let shipped = [
+            ("Glasgows", self.get_fulfilled_glasgow_count(), self.get_glasgow_supply_count()),
+            ("Glasgow Cases", self.get_fulfilled_glasgow_cases_count(), self.get_glasgow_cases_supply_count()),
+        ];
+        for (name, shipped, delivered) in shipped {
+            if shipped > delivered {
+                diagnostics.push(Diagnostic {
+                    kind: DiagnosticKind::ShippedExceedsDelivered,
+                    order_id: None,
+                    message: format!("{} {} shipped to backers, but only {} delivered to Mouser or restocked from returns", shipped, name, delivered)
+                });
+            }
+        }
+
+        ValidationReport {
+            diagnostics
+        }
+    }
+
+    pub fn print_validation(&self) {
+        let report = self.validate();
+        if report.is_clean() {
+            println!("No data quality issues found.");
+            return;
+        }
+        println!("Found {} data quality issues:", report.diagnostics.len());
+        for (kind, diagnostics) in report.by_kind() {
+            println!("{} ({}):", kind, diagnostics.len());
+            for d in diagnostics {
+                match d.order_id {
+                    Some(order_id) => println!(" * Order ID {}: {}", order_id, d.message),
+                    None => println!(" * {}", d.message),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+//! Synthetic datasets and golden file helpers shared by the integration tests
+
+// Every test binary only uses part of the helpers
+#![allow(dead_code)]
+
+use std::{env, fmt::Write, fs, path::{Path, PathBuf}};
+use chrono::{Duration, NaiveDate};
+use cs_data::{glasgow_data::Orders, stalled::StalledRules};
+
+/// Product as it appears in the order export, (part number, product name, unit price)
+pub type Sku = (&'static str, &'static str, &'static str);
+
+pub const GLASGOW: Sku = ("GLASGOW-C3", "Glasgow revC", "145.00");
+pub const GLASGOW_EB: Sku = ("GLASGOW-C3-EB", "Glasgow revC - Early Bird", "125.00");
+pub const CASE: Sku = ("GLASGOW-C3-AL-CASE", "Glasgow Aluminum Case", "55.00");
+pub const CASE_EB: Sku = ("GLASGOW-C3-AL-CASE-EB", "Glasgow Aluminum Case - Early Bird", "45.00");
+pub const STICKER: Sku = ("GLASGOW-STICKER", "Glasgow Sticker Pack", "5.00");
+
+/// All dates in the datasets are days after the campaign start
+pub fn day(offset: i64) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 10, 1).unwrap() + Duration::days(offset)
+}
+
+/// One row of the order export
+pub struct Line {
+    order_id: usize,
+    sku: Sku,
+    qty: usize,
+    country: &'static str,
+    placed: i64,
+    shipped: Option<i64>,
+    tracking: Option<String>
+}
+
+impl Line {
+    /// Mark the row as shipped on the given day, with a UPS tracking number
+    pub fn shipped(&mut self, offset: i64) -> &mut Self {
+        self.shipped = Some(offset);
+        self.tracking = Some(format!("1Z999AA1{:010}", self.order_id));
+        self
+    }
+
+    /// Tracking number without a shipped time, as in exports taken right after labels were printed
+    pub fn tracked(&mut self) -> &mut Self {
+        self.tracking = Some(format!("1Z999AA1{:010}", self.order_id));
+        self
+    }
+}
+
+/// Units delivered to Mouser, one box per delivery
+struct Delivery {
+    date: i64,
+    vendor_pn: &'static str,
+    mouser_pn: &'static str,
+    qty: usize
+}
+
+/// Order export and production data written to a temporary directory
+#[derive(Default)]
+pub struct Dataset {
+    lines: Vec<Line>,
+    deliveries: Vec<Delivery>
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an order row, rows with the same order ID form one order
+    pub fn line(&mut self, order_id: usize, sku: Sku, qty: usize, country: &'static str, placed: i64) -> &mut Line {
+        self.lines.push(Line { order_id, sku, qty, country, placed, shipped: None, tracking: None });
+        self.lines.last_mut().unwrap()
+    }
+
+    pub fn deliver_glasgows(&mut self, date: i64, qty: usize) -> &mut Self {
+        self.deliveries.push(Delivery { date, vendor_pn: "GLASGOW-C3", mouser_pn: "392-GLASGOW-C3", qty });
+        self
+    }
+
+    pub fn deliver_cases(&mut self, date: i64, qty: usize) -> &mut Self {
+        self.deliveries.push(Delivery { date, vendor_pn: "GLASGOW-C3-AL-CASE", mouser_pn: "392-GLASGOWC3AL-CASE", qty });
+        self
+    }
+
+    fn order_csv(&self) -> String {
+        let mut csv = String::from("Order ID,Part Number,Product Name,Qty,Unit Price,Subtotal,Company,Country Code,Placed Time,Shipped Time,Tracking\n");
+        for (n, l) in self.lines.iter().enumerate() {
+            let price: f64 = l.sku.2.parse().unwrap();
+            // Spread the orders over the day so the placed times are unique
+            let placed = format!("{} {:02}:{:02}:00.000000", day(l.placed), n / 60 % 24, n % 60);
+            let shipped = l.shipped.map(|s| format!("{} 15:00:00.000000", day(s))).unwrap_or_default();
+            writeln!(csv, "{},{},{},{},{},{:.2},,{},{},{},{}",
+                l.order_id, l.sku.0, l.sku.1, l.qty, l.sku.2, price * l.qty as f64,
+                l.country, placed, shipped, l.tracking.as_deref().unwrap_or_default()
+            ).unwrap();
+        }
+        csv
+    }
+
+    fn production_csv(&self) -> String {
+        let mut csv = String::from("Date,Order No,Box,Mouser PN,Vendor PN,QTY,Note\n");
+        for d in &self.deliveries {
+            let date = day(d.date);
+            writeln!(csv, "{},{},1,{},{},{},", date.format("%-m/%-d/%Y"), date.format("%Y%m%d001"), d.mouser_pn, d.vendor_pn, d.qty).unwrap();
+        }
+        csv
+    }
+
+    /// Write the CSV files and load them the way the CLI and server do
+    pub fn load(&self) -> Orders {
+        let dir = tempfile::tempdir().unwrap();
+        let order_data = dir.path().join("fulfillment-data.csv");
+        let production_data = dir.path().join("production-data.csv");
+        fs::write(&order_data, self.order_csv()).unwrap();
+        fs::write(&production_data, self.production_csv()).unwrap();
+        let mut orders = Orders::new(order_data.to_str().unwrap(), production_data.to_str().unwrap()).unwrap();
+        orders.calculate_queue();
+        orders
+    }
+}
+
+/// Early bird and regular orders placed interleaved, early birds have to be queued first
+pub fn early_bird_mix(count: usize) -> Dataset {
+    let mut data = Dataset::new();
+    for n in 0..count {
+        let order_id = 110_000 + n * 7;
+        let country = ["us", "de", "gb", "jp"][n % 4];
+        let early_bird = n % 3 == 0;
+        data.line(order_id, if early_bird { GLASGOW_EB } else { GLASGOW }, 1, country, n as i64);
+        if n % 4 == 1 {
+            data.line(order_id, if early_bird { CASE_EB } else { CASE }, 1, country, n as i64);
+        }
+    }
+    data.deliver_glasgows(30, count / 2).deliver_cases(45, count / 8);
+    data
+}
+
+/// Orders with several units per row and several rows per order
+pub fn multi_line_orders() -> Dataset {
+    let mut data = Dataset::new();
+    data.line(120_001, GLASGOW, 2, "us", 0);
+    data.line(120_002, GLASGOW, 1, "de", 1);
+    data.line(120_002, CASE, 3, "de", 1);
+    data.line(120_003, GLASGOW_EB, 1, "fr", 2);
+    data.line(120_003, GLASGOW, 1, "fr", 2);
+    data.line(120_003, CASE_EB, 1, "fr", 2);
+    // The same product in two rows, as after an order edit
+    data.line(120_004, CASE, 1, "nl", 3);
+    data.line(120_004, CASE, 1, "nl", 5);
+    data.line(120_005, GLASGOW, 4, "us", 6);
+    data.deliver_glasgows(10, 3).deliver_cases(10, 5);
+    data
+}
+
+/// Orders with shipped, unshipped and tracked but not yet shipped rows
+pub fn partial_shipments() -> Dataset {
+    let mut data = Dataset::new();
+    data.line(130_001, GLASGOW_EB, 1, "us", 0).shipped(40);
+    data.line(130_001, CASE_EB, 1, "us", 0);
+    data.line(130_002, GLASGOW_EB, 1, "de", 1).shipped(41);
+    data.line(130_002, CASE_EB, 1, "de", 1).shipped(60);
+    data.line(130_003, GLASGOW, 1, "gb", 2).tracked();
+    data.line(130_004, GLASGOW, 2, "ca", 3);
+    data.line(130_005, CASE, 1, "au", 4);
+    // Shipped out of queue order
+    data.line(130_006, GLASGOW, 1, "us", 5).shipped(42);
+    data.deliver_glasgows(30, 3).deliver_cases(55, 1);
+    data
+}
+
+/// Products the tracker does not know how to queue
+pub fn unknown_products() -> Dataset {
+    let mut data = Dataset::new();
+    data.line(140_001, STICKER, 2, "us", 0);
+    data.line(140_002, GLASGOW, 1, "de", 1);
+    data.line(140_002, STICKER, 1, "de", 1);
+    data.line(140_003, STICKER, 1, "se", 2).shipped(20);
+    data.line(140_004, CASE, 1, "us", 3);
+    data.deliver_glasgows(10, 1);
+    data
+}
+
+/// Queue position, status and product queue IDs of every order
+pub fn queue_report(orders: &Orders) -> String {
+    let mut report = String::new();
+    for o in &orders.orders {
+        let items: Vec<String> = o.items.iter()
+            .map(|i| match i.product.queue_id() {
+                Some(id) => format!("{} #{}{}", i.product, id, if i.is_shipped() { " shipped" } else { "" }),
+                None => format!("{}{}", i.product, if i.is_shipped() { " shipped" } else { "" })
+            })
+            .collect();
+        writeln!(report, "{} queue {} {} [{}]: {}", o.cs_id, o.queue_id, o.country, o.status, items.join(", ")).unwrap();
+    }
+    report
+}
+
+/// The numbers shown on the index page, plus the stalled orders as of a fixed day
+pub fn stats_report(orders: &Orders) -> String {
+    let mut report = String::new();
+    writeln!(report, "orders: {}", orders.get_order_count()).unwrap();
+    writeln!(report, "fulfilled: {} ({:.1}%)", orders.get_fulfilled_count(), orders.get_fulfilled_percent()).unwrap();
+    writeln!(report, "remaining: {} ({:.1}%)", orders.get_remaining_count(), orders.get_remaining_percent()).unwrap();
+    writeln!(report, "partially shipped: {}", orders.get_partially_shipped_count()).unwrap();
+    writeln!(report, "glasgows: {} ordered, {} supplied, {} at mouser, {} shipped, {} remaining",
+        orders.glasgow_count, orders.get_glasgow_supply_count(), orders.get_glasgow_at_mouser_count(),
+        orders.get_fulfilled_glasgow_count(), orders.get_remaining_glasgow_count()).unwrap();
+    writeln!(report, "glasgow cases: {} ordered, {} supplied, {} at mouser, {} shipped, {} remaining",
+        orders.glasgow_case_count, orders.get_glasgow_cases_supply_count(), orders.get_glasgow_cases_at_mouser_count(),
+        orders.get_fulfilled_glasgow_cases_count(), orders.get_remaining_glasgow_cases_count()).unwrap();
+    for status in cs_data::glasgow_data::OrderStatus::ALL {
+        writeln!(report, "status {}: {}", status, orders.get_status_count(status)).unwrap();
+    }
+    for request in orders.distributor_requests() {
+        writeln!(report, "requested {}: {}, delivered {}, owed {}", request.name, request.requested, request.delivered, request.owed()).unwrap();
+    }
+    let rules = StalledRules { as_of: day(120), ..StalledRules::default() };
+    for s in orders.stalled_orders(&rules) {
+        writeln!(report, "stalled {}: {}, {} days overdue", s.order.cs_id, s.reason, s.days_overdue).unwrap();
+    }
+    report
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.txt", name))
+}
+
+/// Compare against tests/golden/<name>.txt, run with UPDATE_GOLDEN=1 to accept new output
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("{} is missing, run the tests with UPDATE_GOLDEN=1 to create it", path.display()));
+    assert!(expected == actual, "output differs from {}, run the tests with UPDATE_GOLDEN=1 if the change is intended\n--- expected\n{}--- actual\n{}",
+        path.display(), expected, actual);
+}
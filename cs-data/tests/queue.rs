@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+mod fixtures;
+
+use fixtures::{assert_golden, queue_report};
+
+#[test]
+fn early_bird_mix() {
+    let orders = fixtures::early_bird_mix(24).load();
+    assert_golden("queue_early_bird_mix", &queue_report(&orders));
+}
+
+#[test]
+fn early_birds_are_queued_first() {
+    let orders = fixtures::early_bird_mix(24).load();
+    let early_birds = orders.orders.iter().filter(|o| o.contains_early_bird).count();
+    for o in &orders.orders {
+        assert_eq!(o.queue_id < early_birds, o.contains_early_bird, "order {} queued at {}", o.cs_id, o.queue_id);
+    }
+}
+
+#[test]
+fn multi_line_orders() {
+    let orders = fixtures::multi_line_orders().load();
+    assert_golden("queue_multi_line_orders", &queue_report(&orders));
+}
+
+#[test]
+fn partial_shipments() {
+    let orders = fixtures::partial_shipments().load();
+    assert_golden("queue_partial_shipments", &queue_report(&orders));
+}
+
+#[test]
+fn unknown_products() {
+    let orders = fixtures::unknown_products().load();
+    assert_golden("queue_unknown_products", &queue_report(&orders));
+}
+
+#[test]
+fn product_queue_ids_are_unique() {
+    let orders = fixtures::early_bird_mix(40).load();
+    for is_product in [cs_data::glasgow_data::Product::is_glasgow, cs_data::glasgow_data::Product::is_glasgow_case] {
+        let mut ids: Vec<usize> = orders.orders.iter()
+            .flat_map(|o| o.items.iter())
+            .filter(|i| is_product(&i.product))
+            .filter_map(|i| i.product.queue_id())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..ids.len()).collect::<Vec<_>>());
+    }
+}
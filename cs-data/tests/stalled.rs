@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+mod fixtures;
+
+use cs_data::stalled::{StallReason, StalledRules};
+use fixtures::{day, Dataset, CASE, GLASGOW};
+
+fn rules(as_of: i64) -> StalledRules {
+    StalledRules { as_of: day(as_of), ..StalledRules::default() }
+}
+
+/// (order ID, reason, days overdue) of all stalled orders
+fn stalled(data: &Dataset, rules: &StalledRules) -> Vec<(usize, StallReason, i64)> {
+    data.load().stalled_orders(rules).into_iter()
+        .map(|s| (s.order.cs_id, s.reason, s.days_overdue))
+        .collect()
+}
+
+#[test]
+fn in_stock_after_the_allowed_days() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "us", 0);
+    data.deliver_glasgows(10, 1);
+    assert_eq!(stalled(&data, &rules(24)), []);
+    assert_eq!(stalled(&data, &rules(30)), [(150_001, StallReason::InStock { since: day(10) }, 6)]);
+}
+
+#[test]
+fn in_stock_counts_from_the_last_waiting_unit() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "us", 0);
+    data.line(150_001, CASE, 1, "us", 0);
+    data.deliver_glasgows(10, 1).deliver_cases(20, 1);
+    assert_eq!(stalled(&data, &rules(40)), [(150_001, StallReason::InStock { since: day(20) }, 6)]);
+}
+
+#[test]
+fn waiting_for_stock_is_not_stalled() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "us", 0);
+    data.line(150_001, CASE, 1, "us", 0);
+    data.deliver_glasgows(10, 1);
+    assert_eq!(stalled(&data, &rules(100)), []);
+}
+
+#[test]
+fn overtaken_by_a_later_order() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "us", 0);
+    data.line(150_002, GLASGOW, 1, "us", 1).shipped(20);
+    data.deliver_glasgows(10, 2);
+    let overtaken = StallReason::Overtaken { by_order: 150_002, shipped: day(20) };
+    assert_eq!(stalled(&data, &rules(22)), [(150_001, overtaken.clone(), 2)]);
+    // Overtaken wins over in stock
+    assert_eq!(stalled(&data, &rules(40)), [(150_001, overtaken, 20)]);
+}
+
+#[test]
+fn overtaken_needs_all_units_in_stock() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "us", 0);
+    data.line(150_001, CASE, 1, "us", 0);
+    data.line(150_002, GLASGOW, 1, "us", 1).shipped(20);
+    data.deliver_glasgows(10, 2);
+    // The case has not arrived, so the order could not have shipped with the later one
+    assert_eq!(stalled(&data, &rules(40)), []);
+}
+
+#[test]
+fn embargo() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "ru", 0);
+    data.line(150_002, GLASGOW, 1, "us", 1).shipped(11);
+    data.deliver_glasgows(10, 2);
+    let rules = StalledRules { embargoed_countries: vec!["RU".to_string()], ..rules(12) };
+    // Embargo wins over overtaken and reports from the first day in stock
+    assert_eq!(stalled(&data, &rules), [(150_001, StallReason::Embargo { country: "ru".to_string() }, 2)]);
+}
+
+#[test]
+fn embargo_needs_all_units_in_stock() {
+    let mut data = Dataset::new();
+    data.line(150_001, GLASGOW, 1, "ru", 0);
+    let rules = StalledRules { embargoed_countries: vec!["ru".to_string()], ..rules(100) };
+    assert_eq!(stalled(&data, &rules), []);
+}
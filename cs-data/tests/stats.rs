@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+mod fixtures;
+
+use cs_data::glasgow_data::Orders;
+use fixtures::{assert_golden, stats_report};
+
+#[test]
+fn early_bird_mix() {
+    let orders = fixtures::early_bird_mix(24).load();
+    assert_golden("stats_early_bird_mix", &stats_report(&orders));
+}
+
+#[test]
+fn multi_line_orders() {
+    let orders = fixtures::multi_line_orders().load();
+    assert_golden("stats_multi_line_orders", &stats_report(&orders));
+}
+
+#[test]
+fn partial_shipments() {
+    let orders = fixtures::partial_shipments().load();
+    assert_golden("stats_partial_shipments", &stats_report(&orders));
+}
+
+#[test]
+fn unknown_products() {
+    let orders = fixtures::unknown_products().load();
+    assert_golden("stats_unknown_products", &stats_report(&orders));
+}
+
+#[test]
+fn example_data() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../example-data");
+    let mut orders = Orders::new(&format!("{}/fulfillment-data.csv", dir), &format!("{}/production-data.csv", dir)).unwrap();
+    orders.calculate_queue();
+    orders.load_status_overrides(&format!("{}/status-overrides.csv", dir)).unwrap();
+    orders.load_returns(&format!("{}/returns-data.csv", dir)).unwrap();
+    orders.load_purchase_orders(&format!("{}/purchase-order-data.csv", dir)).unwrap();
+    assert_golden("stats_example_data", &stats_report(&orders));
+}
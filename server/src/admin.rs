@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{collections::BTreeSet, sync::Arc};
+use askama::Template;
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Query, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, routing::{get, post}, Router
+};
+use chrono::NaiveDate;
+use cs_data::{finance::RevenueReport, glasgow_data::{Order, OrderStatus, Orders}, stalled::{self, StalledOrder, StalledRules}, validation::ValidationReport};
+use serde::Deserialize;
+use tracing::{info, warn};
+use crate::{auth::{same_origin, AdminAuth}, config::Config, reload, AppState, HtmlTemplate};
+
+/// Order exports of a large campaign are a few megabytes
+const MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// Input files that can be replaced through the upload form, by form field name
+const INPUTS: [(&str, &str); 5] = [
+    ("fulfillment_data", "Order export (FULFILLMENT_DATA)"),
+    ("production_data", "Deliveries to Mouser (PRODUCTION_DATA)"),
+    ("status_overrides", "Status overrides (STATUS_OVERRIDES)"),
+    ("returns_data", "Returns (RETURNS_DATA)"),
+    ("purchase_order_data", "Mouser purchase orders (PURCHASE_ORDER_DATA)"),
+];
+
+/// Maintainer only pages, all handlers require the `AdminAuth` extractor
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin", get(dashboard_page))
+        .route("/admin/orders", get(orders_page))
+        .route("/admin/stalled.csv", get(stalled_csv))
+        .route("/admin/upload", post(upload).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)))
+        .route("/admin/reload", post(reload_now))
+        .route("/admin/revenue", get(revenue_page))
+        .route("/admin/validation", get(validation_page))
+}
+
+/// Configured path of an input file, only configured inputs can be replaced
+fn input_path<'a>(config: &'a mut Config, name: &str) -> Option<&'a mut String> {
+    match name {
+        "fulfillment_data" => Some(&mut config.fulfillment_data),
+        "production_data" => Some(&mut config.production_data),
+        "status_overrides" => config.status_overrides.as_mut(),
+        "returns_data" => config.returns_data.as_mut(),
+        "purchase_order_data" => config.purchase_order_data.as_mut(),
+        _ => None
+    }
+}
+
+/// Outcome of an upload or reload, shown at the top of the dashboard
+struct Message {
+    success: bool,
+    text: String
+}
+
+fn dashboard(state: &AppState, status: StatusCode, message: Option<Message>) -> Response {
+    let orders = state.orders();
+    let mut config = (*state.config).clone();
+    let inputs = INPUTS.into_iter()
+        .filter(|(name, _)| input_path(&mut config, name).is_some())
+        .collect();
+    let rules = state.config.stalled_rules();
+    let template = DashboardTemplate {
+        stalled: orders.stalled_orders(&rules),
+        rules,
+        report: orders.validate(),
+        orders,
+        inputs,
+        reload_error: state.health.last_error(),
+        message
+    };
+    (status, HtmlTemplate(template)).into_response()
+}
+
+async fn dashboard_page(_: AdminAuth, State(state): State<AppState>) -> Response {
+    dashboard(&state, StatusCode::OK, None)
+}
+
+#[derive(Template)]
+#[template(path = "admin/dashboard.html")]
+struct DashboardTemplate {
+    orders: Arc<Orders>,
+    stalled: Vec<StalledOrder>,
+    rules: StalledRules,
+    report: ValidationReport,
+    inputs: Vec<(&'static str, &'static str)>,
+    /// Error of the last failed reload, the public health endpoints only say that it failed
+    reload_error: Option<String>,
+    message: Option<Message>
+}
+
+/// Stalled orders as CSV for Crowd Supply support
+async fn stalled_csv(_: AdminAuth, State(state): State<AppState>) -> Response {
+    let rules = state.config.stalled_rules();
+    let stalled = state.orders().stalled_orders(&rules);
+    let mut csv = Vec::new();
+    if let Err(err) = stalled::write_stalled_csv(&stalled, &mut csv) {
+        warn!("Could not write the stalled order CSV: {}", err);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"stalled-orders-{}.csv\"", rules.as_of)),
+        ],
+        csv
+    )
+        .into_response()
+}
+
+/// Replace input files with uploaded ones, the files are only replaced if the new data loads
+async fn upload(_: AdminAuth, State(state): State<AppState>, headers: HeaderMap, mut multipart: Multipart) -> Response {
+    if !same_origin(&headers, &state.config.public_url) {
+        return (StatusCode::FORBIDDEN, "Cross site request rejected.").into_response();
+    }
+    let mut config = (*state.config).clone();
+    // (configured path, uploaded copy next to it)
+    let mut staged: Vec<(String, String)> = Vec::new();
+    let mut error = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => {
+                error = Some(format!("Could not read the upload: {}", err));
+                break;
+            }
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        let data = match field.bytes().await {
+            Ok(data) => data,
+            Err(err) => {
+                error = Some(format!("Could not read the upload: {}", err));
+                break;
+            }
+        };
+        // File inputs without a selected file are submitted empty
+        if data.is_empty() {
+            continue;
+        }
+        let Some(path) = input_path(&mut config, &name) else {
+            error = Some(format!("{} is not a configured input.", name));
+            break;
+        };
+        if staged.iter().any(|(_, upload_path)| upload_path == path) {
+            error = Some(format!("{} was uploaded more than once.", name));
+            break;
+        }
+        let upload_path = format!("{}.upload", path);
+        if let Err(err) = tokio::fs::write(&upload_path, &data).await {
+            error = Some(format!("Could not store the upload: {}", err));
+            break;
+        }
+        staged.push((path.clone(), upload_path.clone()));
+        *path = upload_path;
+    }
+
+    let result = match (error, staged.is_empty()) {
+        (Some(error), _) => Err(error),
+        (None, true) => Err("No file was selected.".to_string()),
+        (None, false) => reload::load(Arc::new(config)).await
+            .map_err(|err| format!("The uploaded data could not be loaded, nothing was replaced: {:#}", err))
+    };
+    let orders = match result {
+        Ok(orders) => orders,
+        Err(text) => {
+            for (_, upload_path) in &staged {
+                let _ = tokio::fs::remove_file(upload_path).await;
+            }
+            warn!("Admin upload failed: {}", text);
+            return dashboard(&state, StatusCode::BAD_REQUEST, Some(Message { success: false, text }));
+        }
+    };
+    if let Err(text) = replace_inputs(&state, &staged).await {
+        warn!("Admin upload failed: {}", text);
+        return dashboard(&state, StatusCode::INTERNAL_SERVER_ERROR, Some(Message { success: false, text }));
+    }
+    info!("Admin uploaded {} input files.", staged.len());
+    let changed = reload::publish(&state, orders).await;
+    let text = format!("Replaced {} input files, {} orders changed.", staged.len(), changed);
+    dashboard(&state, StatusCode::OK, Some(Message { success: true, text }))
+}
+
+/// Move the staged uploads over the live inputs, if one of them can not be replaced the already
+/// replaced inputs are put back so the files on disk never stay a mix of old and new data
+async fn replace_inputs(state: &AppState, staged: &[(String, String)]) -> Result<(), String> {
+    // The reload task can load a mix of old and new files between two renames. That is acceptable as
+    // each rename is atomic, so it never sees a partially written file, and the mix is only served until
+    // the checked data is published right after this or, on failure, the restored files are reloaded.
+    let mut replaced = Vec::new();
+    let mut failure = None;
+    for (path, upload_path) in staged {
+        let previous_path = format!("{}.previous", path);
+        if let Err(err) = tokio::fs::copy(path, &previous_path).await {
+            failure = Some(format!("Could not keep a copy of {}: {}", path, err));
+            break;
+        }
+        if let Err(err) = tokio::fs::rename(upload_path, path).await {
+            failure = Some(format!("Could not replace {}: {}", path, err));
+            break;
+        }
+        replaced.push((path, previous_path));
+    }
+    let mut restore_error = None;
+    if failure.is_some() {
+        for (path, previous_path) in replaced.iter().rev() {
+            if let Err(err) = tokio::fs::rename(previous_path, path).await {
+                restore_error = Some(format!("could not restore {} from {}: {}", path, previous_path, err));
+            }
+        }
+    }
+    for (path, upload_path) in staged {
+        let _ = tokio::fs::remove_file(upload_path).await;
+        // Copies that could not be put back are kept for restoring them by hand
+        if restore_error.is_none() {
+            let _ = tokio::fs::remove_file(format!("{}.previous", path)).await;
+        }
+    }
+    match (failure, restore_error) {
+        (None, _) => Ok(()),
+        (Some(failure), None) => Err(format!("{}, the previous files were restored.", failure)),
+        (Some(failure), Some(restore_error)) => {
+            // The inputs on disk are now inconsistent, keep that visible on the dashboard until the next good reload
+            let text = format!("{}, {}", failure, restore_error);
+            state.health.reload_failed(text.clone());
+            Err(text)
+        }
+    }
+}
+
+/// Reload the input files right away instead of waiting for the reload task
+async fn reload_now(_: AdminAuth, State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !same_origin(&headers, &state.config.public_url) {
+        return (StatusCode::FORBIDDEN, "Cross site request rejected.").into_response();
+    }
+    info!("Admin triggered a reload.");
+    match reload::load(state.config.clone()).await {
+        Ok(orders) => {
+            let changed = reload::publish(&state, orders).await;
+            let text = format!("Data reloaded, {} orders changed.", changed);
+            dashboard(&state, StatusCode::OK, Some(Message { success: true, text }))
+        }
+        Err(err) => {
+            let text = format!("Reload failed, keeping the previous data: {:#}", err);
+            warn!("{}", text);
+            state.health.reload_failed(format!("{:#}", err));
+            dashboard(&state, StatusCode::INTERNAL_SERVER_ERROR, Some(Message { success: false, text }))
+        }
+    }
+}
+
+/// Queue table filters, all optional
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct QueueQuery {
+    /// Matches order IDs, countries, companies, SKUs and product names
+    q: String,
+    status: String,
+    country: String,
+    sort: String,
+    dir: String
+}
+
+/// Columns the queue table can be sorted by, (value, label)
+const SORT_COLUMNS: [(&str, &str); 6] = [
+    ("queue", "Queue ID"),
+    ("id", "Order ID"),
+    ("date", "Order date"),
+    ("country", "Country"),
+    ("status", "Status"),
+    ("items", "Items"),
+];
+
+struct QueueRow {
+    cs_id: usize,
+    queue_id: usize,
+    date: NaiveDate,
+    country: String,
+    company: String,
+    status: OrderStatus,
+    note: String,
+    items: String,
+    item_count: usize,
+    shipped_count: usize
+}
+
+impl QueueRow {
+    fn new(order: &Order) -> Self {
+        let items = order.items.iter()
+            .map(|i| match i.product.queue_id() {
+                Some(id) => format!("{} #{}", i.product, id),
+                None => i.product.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self {
+            cs_id: order.cs_id,
+            queue_id: order.queue_id,
+            date: order.date,
+            country: order.country.clone(),
+            company: order.company.clone().unwrap_or_default(),
+            status: order.status,
+            note: order.status_note.clone().unwrap_or_default(),
+            items,
+            item_count: order.items.len(),
+            shipped_count: order.shipped_item_count()
+        }
+    }
+
+    fn matches(&self, order: &Order, search: &str) -> bool {
+        search.is_empty()
+            || self.cs_id.to_string().contains(search)
+            || self.country.to_lowercase().contains(search)
+            || self.company.to_lowercase().contains(search)
+            || self.items.to_lowercase().contains(search)
+            || order.items.iter().any(|i| i.sku.to_lowercase().contains(search))
+    }
+}
+
+async fn orders_page(_: AdminAuth, State(state): State<AppState>, Query(query): Query<QueueQuery>) -> impl IntoResponse {
+    let orders = state.orders();
+    let search = query.q.trim().to_lowercase();
+    let status = query.status.parse::<OrderStatus>().ok();
+    let mut rows: Vec<QueueRow> = orders.orders.iter()
+        .filter(|o| status.is_none_or(|s| o.status == s))
+        .filter(|o| query.country.is_empty() || o.country.eq_ignore_ascii_case(&query.country))
+        .filter_map(|o| {
+            let row = QueueRow::new(o);
+            row.matches(o, &search).then_some(row)
+        })
+        .collect();
+    match query.sort.as_str() {
+        "id" => rows.sort_by_key(|r| r.cs_id),
+        "date" => rows.sort_by_key(|r| (r.date, r.cs_id)),
+        "country" => rows.sort_by(|a, b| a.country.cmp(&b.country).then(a.queue_id.cmp(&b.queue_id))),
+        "status" => rows.sort_by(|a, b| a.status.to_string().cmp(&b.status.to_string()).then(a.queue_id.cmp(&b.queue_id))),
+        "items" => rows.sort_by_key(|r| (r.item_count, r.queue_id)),
+        _ => rows.sort_by_key(|r| r.queue_id),
+    }
+    if query.dir == "desc" {
+        rows.reverse();
+    }
+    let countries = orders.orders.iter().map(|o| o.country.clone()).collect::<BTreeSet<_>>().into_iter().collect();
+    HtmlTemplate(OrdersTemplate {
+        total: orders.orders.len(),
+        rows,
+        countries,
+        query
+    })
+}
+
+#[derive(Template)]
+#[template(path = "admin/orders.html")]
+struct OrdersTemplate {
+    total: usize,
+    rows: Vec<QueueRow>,
+    countries: Vec<String>,
+    query: QueueQuery
+}
+
+impl OrdersTemplate {
+    fn status_selected(&self, status: &OrderStatus) -> bool {
+        self.query.status.parse::<OrderStatus>().ok().as_ref() == Some(status)
+    }
+}
+
+async fn revenue_page(_: AdminAuth, State(state): State<AppState>) -> impl IntoResponse {
+    let report = state.orders().revenue_report();
+    HtmlTemplate(RevenueTemplate { report })
+}
+
+#[derive(Template)]
+#[template(path = "admin/revenue.html")]
+struct RevenueTemplate {
+    report: RevenueReport
+}
+
+async fn validation_page(_: AdminAuth, State(state): State<AppState>) -> impl IntoResponse {
+    let report = state.orders().validate();
+    HtmlTemplate(ValidationTemplate { report })
+}
+
+#[derive(Template)]
+#[template(path = "admin/validation.html")]
+struct ValidationTemplate {
+    report: ValidationReport
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{collections::HashMap, path::Path as FsPath, sync::OnceLock};
+use anyhow::Context;
+use axum::{extract::Path, http::{header, StatusCode}, response::{IntoResponse, Response}, routing::get, Router};
+use rust_embed::RustEmbed;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use crate::AppState;
+
+/// Static files compiled into the binary, so the server needs nothing but the data files
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Embedded;
+
+/// Static file loaded into memory
+struct Asset {
+    content: Vec<u8>,
+    content_type: &'static str,
+    /// Path with the content hash, for example css/tracker.0123456789abcdef.css
+    hashed_path: String
+}
+
+/// All static files by their plain path, for example css/tracker.css
+struct Assets {
+    files: HashMap<String, Asset>
+}
+
+static ASSETS: OnceLock<Assets> = OnceLock::new();
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream"
+    }
+}
+
+/// Insert the content hash in front of the extension
+fn hashed_path(path: &str, content: &[u8]) -> String {
+    let digest = Sha256::digest(content);
+    let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !stem.ends_with('/') => format!("{}.{}.{}", stem, hash, ext),
+        _ => format!("{}.{}", path, hash)
+    }
+}
+
+impl Asset {
+    fn new(path: &str, content: Vec<u8>) -> Self {
+        Self {
+            hashed_path: hashed_path(path, &content),
+            content_type: content_type(path),
+            content
+        }
+    }
+}
+
+/// Read all files below a directory, keyed by their path relative to the directory
+fn read_dir(root: &FsPath, dir: &FsPath, files: &mut HashMap<String, Asset>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("could not read {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_dir(root, &path, files)?;
+        } else {
+            let name = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+            files.insert(name.clone(), Asset::new(&name, std::fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+/// Load the static files, from STATIC_DIR when set or the copy embedded in the binary otherwise
+pub fn init(static_dir: Option<&str>) -> anyhow::Result<()> {
+    let mut files = HashMap::new();
+    match static_dir {
+        Some(dir) => {
+            info!("Serving static files from {:?}", dir);
+            read_dir(FsPath::new(dir), FsPath::new(dir), &mut files)?;
+        }
+        None => {
+            for name in Embedded::iter() {
+                if let Some(file) = Embedded::get(&name) {
+                    files.insert(name.to_string(), Asset::new(&name, file.data.into_owned()));
+                }
+            }
+        }
+    }
+    // Only the first call counts, the files do not change while running
+    let _ = ASSETS.set(Assets { files });
+    Ok(())
+}
+
+/// URL of a static file including its content hash, used by the templates
+pub fn url(path: &str) -> String {
+    match ASSETS.get().and_then(|assets| assets.files.get(path)) {
+        Some(asset) => format!("/static/{}", asset.hashed_path),
+        None => format!("/static/{}", path)
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/static/*path", get(static_file))
+}
+
+/// Hashed URLs never change their content and can be cached forever, plain URLs have to be revalidated
+async fn static_file(Path(path): Path<String>) -> Response {
+    let Some(assets) = ASSETS.get() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let found = assets.files.values().find(|a| a.hashed_path == path).map(|a| (a, "public, max-age=31536000, immutable"))
+        .or_else(|| assets.files.get(&path).map(|a| (a, "no-cache")));
+    match found {
+        Some((asset, cache_control)) => (
+            [(header::CONTENT_TYPE, asset.content_type), (header::CACHE_CONTROL, cache_control)],
+            asset.content.clone(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
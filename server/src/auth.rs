@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use axum::{
+    async_trait, extract::{FromRef, FromRequestParts}, http::{header, request::Parts, HeaderMap, StatusCode}, response::{IntoResponse, Response}
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::time::Duration;
+use tracing::info;
+use crate::{limit::request_client, AppState};
+
+/// Failed admin logins allowed per client address within FAILED_LOGIN_WINDOW
+pub const FAILED_LOGIN_LIMIT: u32 = 10;
+pub const FAILED_LOGIN_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Extractor guarding the admin pages, either with HTTP basic auth against the configured admin password
+/// or with the admin token as a bearer token for scripts
+pub struct AdminAuth;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminAuth
+    where
+        AppState: FromRef<S>,
+        S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        let password = state.config.admin_password.as_deref();
+        let token = state.config.admin_token.as_deref();
+        if password.is_none() && token.is_none() {
+            // Without a password or token the admin pages do not exist
+            return Err(StatusCode::NOT_FOUND.into_response());
+        }
+
+        // Clients that guessed wrong too often are turned away before the credentials are even looked at
+        let client = request_client(parts);
+        if let Some(retry_after) = state.admin_limiter.retry_after(client) {
+            info!("Admin login rate limit reached.");
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+                "Too many failed logins, please try again later.",
+            )
+                .into_response());
+        }
+
+        let authorization = parts.headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok());
+        let provided_password = authorization
+            .and_then(|v| v.strip_prefix("Basic "))
+            .and_then(|v| STANDARD.decode(v).ok())
+            .and_then(|v| String::from_utf8(v).ok())
+            .and_then(|v| v.split_once(':').map(|(_, p)| p.to_string()));
+        let provided_token = authorization.and_then(|v| v.strip_prefix("Bearer "));
+
+        let password_valid = matches!((&provided_password, password), (Some(provided), Some(password)) if constant_time_eq(provided.as_bytes(), password.as_bytes()));
+        let token_valid = matches!((provided_token, token), (Some(provided), Some(token)) if constant_time_eq(provided.trim().as_bytes(), token.as_bytes()));
+        if password_valid || token_valid {
+            return Ok(AdminAuth);
+        }
+        if authorization.is_some() {
+            info!("Admin login failed.");
+            let _ = state.admin_limiter.check(client);
+        }
+        Err((
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"Glasgow tracker admin\"")],
+            "Authentication required.",
+        )
+            .into_response())
+    }
+}
+
+/// Compare secrets without leaking the position of the first mismatch through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Browsers send the Origin header with form posts, reject posts made from other sites with the stored credentials
+///
+/// The origin is compared against PUBLIC_URL, the Host header is chosen by the client.
+pub fn same_origin(headers: &HeaderMap, public_url: &str) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        // Not a browser
+        return true;
+    };
+    match (reqwest::Url::parse(origin), reqwest::Url::parse(public_url)) {
+        (Ok(origin), Ok(public_url)) => origin.origin() == public_url.origin(),
+        _ => false
+    }
+}
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use askama::Template;
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, routing::get, Json, Router};
+use chrono::NaiveDate;
+use cs_data::{catalog, timeline::{ProductTimeline, TimelinePoint}};
+use crate::{embed::cached, errors::render_error, ApiError, AppState};
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 280.0;
+const LEFT: f64 = 56.0;
+const RIGHT: f64 = 16.0;
+const TOP: f64 = 36.0;
+const BOTTOM: f64 = 28.0;
+
+/// Progress charts rendered on the server, so the page works without any JavaScript
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/chart/:name", get(chart))
+}
+
+/// URL name of a catalog product, for example glasgow-case
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+async fn chart(State(state): State<AppState>, Path(name): Path<String>, headers: HeaderMap) -> Response {
+    let entry = name.strip_suffix(".svg")
+        .and_then(|name| catalog::CATALOG.iter().find(|e| slug(e.name) == name));
+    let Some(entry) = entry else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError { error: format!("There is no chart called {}.", name) }),
+        )
+            .into_response();
+    };
+    match ChartTemplate::new(&state.orders().timeline(entry)).render() {
+        Ok(svg) => cached(&headers, "image/svg+xml", svg),
+        Err(err) => render_error(err),
+    }
+}
+
+/// Round up to 1, 2 or 5 times a power of ten, so the axis labels are even numbers
+fn nice_max(value: usize) -> usize {
+    let mut magnitude = 1;
+    loop {
+        for step in [1, 2, 5] {
+            if step * magnitude >= value {
+                return step * magnitude;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+/// Name, color and value of a chart line
+type Line = (&'static str, &'static str, fn(&TimelinePoint) -> usize);
+
+struct Series {
+    name: &'static str,
+    color: &'static str,
+    /// SVG polyline points
+    points: String,
+    last: usize,
+    legend_x: f64
+}
+
+struct Tick {
+    pos: f64,
+    label: String
+}
+
+/// Cumulative placed, shipped and delivered units as step lines
+#[derive(Template)]
+#[template(path = "chart.svg")]
+struct ChartTemplate {
+    title: String,
+    width: f64,
+    height: f64,
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+    series: Vec<Series>,
+    x_ticks: Vec<Tick>,
+    y_ticks: Vec<Tick>
+}
+
+impl ChartTemplate {
+    fn new(timeline: &ProductTimeline) -> Self {
+        let (right, bottom) = (WIDTH - RIGHT, HEIGHT - BOTTOM);
+        let mut chart = Self {
+            title: format!("{} units over time", timeline.name),
+            width: WIDTH,
+            height: HEIGHT,
+            left: LEFT,
+            right,
+            top: TOP,
+            bottom,
+            series: Vec::new(),
+            x_ticks: Vec::new(),
+            y_ticks: Vec::new()
+        };
+        let (Some(first), Some(last)) = (timeline.points.first(), timeline.points.last()) else {
+            return chart;
+        };
+        let days = (last.date - first.date).num_days().max(1) as f64;
+        let x = |date: NaiveDate| LEFT + (date - first.date).num_days() as f64 / days * (right - LEFT);
+        let y_max = nice_max(last.placed.max(last.shipped).max(last.delivered).max(1));
+        let y = |value: usize| bottom - value as f64 / y_max as f64 * (bottom - TOP);
+
+        let lines: [Line; 3] = [
+            ("Ordered", "#0d6efd", |p| p.placed),
+            ("Delivered to Mouser", "#0dcaf0", |p| p.delivered),
+            ("Shipped", "#198754", |p| p.shipped),
+        ];
+        for (i, (name, color, value)) in lines.into_iter().enumerate() {
+            // Step line, the count stays flat until the next day with activity
+            let mut points = format!("{:.1},{:.1}", x(first.date), y(0));
+            let mut previous = 0;
+            for p in &timeline.points {
+                points += &format!(" {:.1},{:.1} {:.1},{:.1}", x(p.date), y(previous), x(p.date), y(value(p)));
+                previous = value(p);
+            }
+            chart.series.push(Series { name, color, points, last: value(last), legend_x: LEFT + 180.0 * i as f64 });
+        }
+        chart.y_ticks = (0..=4).map(|i| {
+            let value = y_max * i / 4;
+            Tick { pos: y(value), label: value.to_string() }
+        }).collect();
+        chart.x_ticks = (0..=3).map(|i| {
+            let date = first.date + chrono::Duration::days((days * i as f64 / 3.0).round() as i64);
+            Tick { pos: x(date), label: date.format("%Y-%m-%d").to_string() }
+        }).collect();
+        chart
+    }
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use askama::Template;
+use axum::{extract::{Path, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, routing::get, Json, Router};
+use cs_data::glasgow_data::Orders;
+use sha2::{Digest, Sha256};
+use crate::{errors::render_error, ApiError, AppState};
+
+/// Browsers and CDNs may reuse badges and widgets for this long, the data changes rarely
+const MAX_AGE_SECONDS: u32 = 300;
+
+/// Badges and widgets meant to be embedded into other sites
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/badge/:name", get(badge))
+        .route("/widget", get(widget))
+}
+
+/// Serve with cache headers, answering revalidation requests with 304 when the content did not change
+pub(crate) fn cached(request_headers: &HeaderMap, content_type: &'static str, body: String) -> Response {
+    let digest = Sha256::digest(body.as_bytes());
+    let etag = format!("\"{}\"", digest[..8].iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let cache_control = format!("public, max-age={}", MAX_AGE_SECONDS);
+    let not_modified = request_headers.get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)]).into_response();
+    }
+    ([(header::CONTENT_TYPE, content_type.to_string()), (header::ETAG, etag), (header::CACHE_CONTROL, cache_control)], body).into_response()
+}
+
+/// Percentages of a product nobody ordered divide by zero, show them as 0%
+fn or_zero(percent: f64) -> f64 {
+    if percent.is_finite() { percent } else { 0.0 }
+}
+
+/// Available badges, addressed as /badge/<name>.svg
+fn badge_for(name: &str, orders: &Orders) -> Option<BadgeTemplate> {
+    let (label, percent) = match name {
+        "orders" => ("orders fulfilled", orders.get_fulfilled_percent()),
+        "glasgow" => ("Glasgows shipped", orders.get_fulfilled_glasgow_percent()),
+        "glasgow-case" => ("Glasgow Cases shipped", orders.get_fulfilled_glasgow_cases_percent()),
+        "glasgow-at-mouser" => ("Glasgows at Mouser", orders.get_glasgow_at_mouser_percent()),
+        "glasgow-case-at-mouser" => ("Glasgow Cases at Mouser", orders.get_glasgow_cases_at_mouser_percent()),
+        _ => return None
+    };
+    Some(BadgeTemplate::new(label, or_zero(percent)))
+}
+
+async fn badge(State(state): State<AppState>, Path(name): Path<String>, headers: HeaderMap) -> Response {
+    let badge = name.strip_suffix(".svg").and_then(|name| badge_for(name, &state.orders()));
+    let Some(badge) = badge else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError { error: format!("There is no badge called {}.", name) }),
+        )
+            .into_response();
+    };
+    match badge.render() {
+        Ok(svg) => cached(&headers, "image/svg+xml", svg),
+        Err(err) => render_error(err),
+    }
+}
+
+/// Shields style badge, the text widths are estimated for 11px Verdana
+#[derive(Template)]
+#[template(path = "badge.svg")]
+struct BadgeTemplate {
+    label: String,
+    value: String,
+    color: &'static str,
+    label_width: usize,
+    value_width: usize
+}
+
+impl BadgeTemplate {
+    fn new(label: &str, percent: f64) -> Self {
+        let value = format!("{:.0}%", percent);
+        let text_width = |s: &str| s.chars().count() * 7 + 10;
+        let color = match percent {
+            p if p >= 100.0 => "#4c1",
+            p if p >= 50.0 => "#97ca00",
+            p if p >= 25.0 => "#dfb317",
+            _ => "#fe7d37"
+        };
+        Self {
+            label_width: text_width(label),
+            value_width: text_width(&value),
+            label: label.to_string(),
+            value,
+            color
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.label_width + self.value_width
+    }
+}
+
+/// Progress of one product for the widget bars
+struct SkuProgress {
+    name: &'static str,
+    ordered: usize,
+    fulfilled: usize,
+    fulfilled_percent: f64,
+    at_mouser: i32,
+    at_mouser_percent: f64
+}
+
+impl SkuProgress {
+    fn fulfilled_width(&self) -> f64 {
+        self.fulfilled_percent.clamp(0.0, 100.0)
+    }
+
+    /// Stacked on top of the fulfilled bar, so the bars never add up to more than 100%
+    fn at_mouser_width(&self) -> f64 {
+        self.at_mouser_percent.clamp(0.0, 100.0 - self.fulfilled_width())
+    }
+}
+
+async fn widget(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let orders = state.orders();
+    let template = WidgetTemplate {
+        fulfilled_orders: orders.get_fulfilled_count(),
+        order_count: orders.get_order_count(),
+        public_url: state.config.public_url.trim_end_matches('/').to_string(),
+        products: vec![
+            SkuProgress {
+                name: "Glasgow",
+                ordered: orders.glasgow_count,
+                fulfilled: orders.get_fulfilled_glasgow_count(),
+                fulfilled_percent: or_zero(orders.get_fulfilled_glasgow_percent()),
+                at_mouser: orders.get_glasgow_at_mouser_count(),
+                at_mouser_percent: or_zero(orders.get_glasgow_at_mouser_percent())
+            },
+            SkuProgress {
+                name: "Glasgow Case",
+                ordered: orders.glasgow_case_count,
+                fulfilled: orders.get_fulfilled_glasgow_cases_count(),
+                fulfilled_percent: or_zero(orders.get_fulfilled_glasgow_cases_percent()),
+                at_mouser: orders.get_glasgow_cases_at_mouser_count(),
+                at_mouser_percent: or_zero(orders.get_glasgow_cases_at_mouser_percent())
+            },
+        ]
+    };
+    match template.render() {
+        Ok(html) => cached(&headers, "text/html; charset=utf-8", html),
+        Err(err) => render_error(err),
+    }
+}
+
+/// Compact progress overview meant to be embedded with an iframe
+#[derive(Template)]
+#[template(path = "widget.html")]
+struct WidgetTemplate {
+    fulfilled_orders: usize,
+    order_count: usize,
+    public_url: String,
+    products: Vec<SkuProgress>
+}
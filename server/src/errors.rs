@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use askama::Template;
+use axum::{http::StatusCode, response::{Html, IntoResponse, Response}};
+use cs_data::i18n::Locale;
+use tracing::error;
+use crate::locale::Lang;
+
+/// Styled error page, the message defaults to a generic explanation of the status
+pub fn error_page(status: StatusCode, locale: Locale, message: Option<String>) -> Response {
+    let (title, default_message) = match status {
+        StatusCode::BAD_REQUEST => ("error.bad_request", "error.bad_request_message"),
+        StatusCode::NOT_FOUND => ("error.not_found", "error.not_found_message"),
+        StatusCode::TOO_MANY_REQUESTS => ("error.too_many_requests", "error.too_many_requests_message"),
+        _ => ("error.internal", "error.internal_message"),
+    };
+    let template = ErrorTemplate {
+        locale,
+        code: status.as_u16(),
+        title: locale.tr(title),
+        message: message.unwrap_or_else(|| locale.tr(default_message).to_string())
+    };
+    match template.render() {
+        Ok(html) => (status, Html(html)).into_response(),
+        Err(err) => {
+            error!("Failed to render the error page: {}", err);
+            (status, status.canonical_reason().unwrap_or_default()).into_response()
+        }
+    }
+}
+
+/// Answer a failed template with the error page, the details only go into the log
+pub fn render_error(err: askama::Error) -> Response {
+    error!("Failed to render template: {}", err);
+    error_page(StatusCode::INTERNAL_SERVER_ERROR, Locale::default(), None)
+}
+
+/// Fallback for all paths without a route
+pub async fn not_found(Lang(locale): Lang) -> Response {
+    error_page(StatusCode::NOT_FOUND, locale, None)
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    locale: Locale,
+    code: u16,
+    title: &'static str,
+    message: String
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::sync::Mutex;
+use askama::Template;
+use axum::{extract::State, http::header, response::{IntoResponse, Response}, routing::get, Router};
+use chrono::{DateTime, Utc};
+use cs_data::{changes::CampaignUpdate, glasgow_data::Orders};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::{errors::render_error, persist::{load_json, save_json}, AppState};
+
+/// Number of entries kept in the feed
+const MAX_ENTRIES: usize = 50;
+
+/// Fulfillment progress published in the feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEntry {
+    pub time: DateTime<Utc>,
+    pub title: String,
+    /// Overall campaign progress at the time of the entry
+    pub summary: String,
+    pub update: CampaignUpdate
+}
+
+/// Entries of the Atom feed, newest first, persisted as JSON when FEED_DATA is set
+pub struct Feed {
+    path: Option<String>,
+    started: DateTime<Utc>,
+    entries: Mutex<Vec<FeedEntry>>
+}
+
+impl Feed {
+    pub fn load(path: Option<String>) -> anyhow::Result<Self> {
+        let entries = load_json(path.as_deref())?;
+        Ok(Self {
+            path,
+            started: Utc::now(),
+            entries: Mutex::new(entries)
+        })
+    }
+
+    /// Publish an entry for the progress since the last reload, nothing is published if nothing moved
+    pub fn record(&self, orders: &Orders, update: CampaignUpdate) -> anyhow::Result<()> {
+        if update.is_empty() {
+            return Ok(());
+        }
+        let entry = FeedEntry {
+            time: Utc::now(),
+            title: update.to_string(),
+            summary: format!("{} of {} orders are fulfilled. Mouser has received {} Glasgows and {} Glasgow Cases so far.",
+                orders.get_fulfilled_count(),
+                orders.get_order_count(),
+                orders.get_glasgow_supply_count(),
+                orders.get_glasgow_cases_supply_count()),
+            update
+        };
+        info!("New feed entry: {}", entry.title);
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(0, entry);
+        entries.truncate(MAX_ENTRIES);
+        save_json(self.path.as_deref(), &*entries)
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/feed.atom", get(feed_atom))
+}
+
+async fn feed_atom(State(state): State<AppState>) -> Response {
+    let entries = state.feed.entries.lock().unwrap().clone();
+    let template = FeedTemplate {
+        public_url: state.config.public_url.trim_end_matches('/').to_string(),
+        updated: entries.first().map(|e| e.time).unwrap_or(state.feed.started),
+        entries
+    };
+    match template.render() {
+        Ok(xml) => ([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], xml).into_response(),
+        Err(err) => render_error(err),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "feed.xml")]
+struct FeedTemplate {
+    public_url: String,
+    updated: DateTime<Utc>,
+    entries: Vec<FeedEntry>
+}
@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::sync::Mutex;
+use axum::{extract::State, http::{header, StatusCode}, response::{IntoResponse, Response}, routing::get, Json, Router};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Serialize;
+use crate::{config::Config, AppState};
+
+/// Outcome of the data loading, updated by the reload task
+pub struct Health {
+    started: DateTime<Utc>,
+    status: Mutex<LoadStatus>
+}
+
+#[derive(Clone)]
+struct LoadStatus {
+    loaded_at: DateTime<Utc>,
+    /// Error of the last reload, the previous data is still served
+    last_error: Option<String>
+}
+
+impl Health {
+    /// Created once the initial data is loaded
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            started: now,
+            status: Mutex::new(LoadStatus { loaded_at: now, last_error: None })
+        }
+    }
+
+    pub fn reloaded(&self) {
+        *self.status.lock().unwrap() = LoadStatus { loaded_at: Utc::now(), last_error: None };
+    }
+
+    pub fn reload_failed(&self, error: String) {
+        self.status.lock().unwrap().last_error = Some(error);
+    }
+
+    /// Error of the last reload, only shown on the admin pages as it contains file paths
+    pub fn last_error(&self) -> Option<String> {
+        self.status.lock().unwrap().last_error.clone()
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Readiness {
+    Ok,
+    /// The order export has no orders
+    NoData,
+    /// The last reload failed
+    ReloadFailed,
+    /// The order export is older than DATA_MAX_AGE_HOURS
+    Stale
+}
+
+#[derive(Serialize)]
+struct RecordCounts {
+    orders: usize,
+    order_rows: usize,
+    production_rows: usize,
+    shipments: usize,
+    returns: usize
+}
+
+/// Input file by the name of its config variable, the path is not published
+#[derive(Serialize)]
+struct InputFile {
+    name: &'static str,
+    modified: Option<DateTime<Utc>>
+}
+
+#[derive(Serialize)]
+struct HealthReport {
+    status: Readiness,
+    data_loaded: bool,
+    started: DateTime<Utc>,
+    loaded_at: DateTime<Utc>,
+    reload_failed: bool,
+    newest_placed_time: Option<NaiveDateTime>,
+    newest_shipped_time: Option<NaiveDateTime>,
+    newest_delivery_date: Option<NaiveDate>,
+    records: RecordCounts,
+    files: Vec<InputFile>
+}
+
+fn input_files(config: &Config) -> Vec<InputFile> {
+    [
+        ("FULFILLMENT_DATA", Some(&config.fulfillment_data)),
+        ("PRODUCTION_DATA", Some(&config.production_data)),
+        ("STATUS_OVERRIDES", config.status_overrides.as_ref()),
+        ("RETURNS_DATA", config.returns_data.as_ref()),
+        ("PURCHASE_ORDER_DATA", config.purchase_order_data.as_ref()),
+    ]
+        .into_iter()
+        .filter_map(|(name, path)| Some(InputFile {
+            name,
+            modified: std::fs::metadata(path?).and_then(|m| m.modified()).ok().map(DateTime::from)
+        }))
+        .collect()
+}
+
+fn report(state: &AppState) -> HealthReport {
+    let orders = state.orders();
+    let status = state.health.status.lock().unwrap().clone();
+    let data_loaded = !orders.orders.is_empty();
+    let files = input_files(&state.config);
+    let export_age = files.first()
+        .and_then(|f| f.modified)
+        .map(|modified| Utc::now().signed_duration_since(modified));
+    let stale = match (state.config.data_max_age_hours, export_age) {
+        (Some(hours), Some(age)) => age.num_hours() >= hours,
+        // A missing export is as stale as it gets
+        (Some(_), None) => true,
+        (None, _) => false
+    };
+    HealthReport {
+        status: if !data_loaded {
+            Readiness::NoData
+        } else if status.last_error.is_some() {
+            Readiness::ReloadFailed
+        } else if stale {
+            Readiness::Stale
+        } else {
+            Readiness::Ok
+        },
+        data_loaded,
+        started: state.health.started,
+        loaded_at: status.loaded_at,
+        reload_failed: status.last_error.is_some(),
+        newest_placed_time: orders.newest_placed_time,
+        newest_shipped_time: orders.newest_shipped_time(),
+        newest_delivery_date: orders.newest_delivery_date(),
+        records: RecordCounts {
+            orders: orders.orders.len(),
+            order_rows: orders.order_record_count,
+            production_rows: orders.production_record_count,
+            shipments: orders.shipments.len(),
+            returns: orders.returns.len()
+        },
+        files
+    }
+}
+
+/// Liveness, answers as long as the server is running
+async fn healthz(State(state): State<AppState>) -> Response {
+    ([(header::CACHE_CONTROL, "no-store")], Json(report(&state))).into_response()
+}
+
+/// Readiness, fails when the data could not be reloaded or the order export is stale
+async fn readyz(State(state): State<AppState>) -> Response {
+    let report = report(&state);
+    let status = match report.status {
+        Readiness::Ok => StatusCode::OK,
+        _ => StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, [(header::CACHE_CONTROL, "no-store")], Json(report)).into_response()
+}
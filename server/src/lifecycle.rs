@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::time::Duration;
+use anyhow::Context;
+use axum_server::Handle;
+use listenfd::ListenFd;
+use tracing::info;
+
+/// Listening socket handed over by systemd socket activation (LISTEN_FDS) or systemfd, if any
+pub fn inherited_listener() -> anyhow::Result<Option<std::net::TcpListener>> {
+    ListenFd::from_env()
+        .take_tcp_listener(0)
+        .context("the inherited socket is not a TCP listener")
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Stop accepting connections on a shutdown signal and give in-flight requests time to finish
+pub async fn graceful_shutdown(handle: Handle, timeout: Duration) {
+    shutdown_signal().await;
+    info!("Shutting down, waiting up to {} seconds for {} open connections...", timeout.as_secs(), handle.connection_count());
+    handle.graceful_shutdown(Some(timeout));
+}
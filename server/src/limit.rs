@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{collections::HashMap, net::{IpAddr, Ipv4Addr, SocketAddr}, sync::Mutex, time::{Duration, Instant}};
+use axum::{
+    async_trait, extract::{ConnectInfo, FromRef, FromRequestParts}, http::{header, request::Parts, HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json
+};
+use tracing::info;
+use crate::{errors::error_page, locale::Lang, ApiError, AppState};
+
+/// Forget about clients that have been quiet for a full window once this many are tracked
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Fixed window request counter per client address
+pub struct RateLimiter {
+    /// Requests allowed per window, zero disables the limit
+    limit: u32,
+    window: Duration,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window, clients: Mutex::new(HashMap::new()) }
+    }
+
+    /// Count a request, returns the time until the client may try again when it is over the limit
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_TRACKED_CLIENTS {
+            clients.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+        }
+        let (start, count) = clients.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            return Err(self.window.saturating_sub(now.duration_since(*start)));
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    /// Time until the client may try again when it is over the limit, without counting a request
+    pub fn retry_after(&self, client: IpAddr) -> Option<Duration> {
+        if self.limit == 0 {
+            return None;
+        }
+        let now = Instant::now();
+        let clients = self.clients.lock().unwrap();
+        let (start, count) = clients.get(&client)?;
+        let elapsed = now.duration_since(*start);
+        (elapsed < self.window && *count >= self.limit).then(|| self.window - elapsed)
+    }
+}
+
+/// Address of the client, taken from X-Forwarded-For when the connection comes from a local reverse proxy
+fn client_address(peer: Option<IpAddr>, headers: &HeaderMap) -> IpAddr {
+    let peer = peer.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    if !peer.is_loopback() {
+        return peer;
+    }
+    headers.get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Address of the client a request came from
+pub fn request_client(parts: &Parts) -> IpAddr {
+    let peer = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|c| c.0.ip());
+    client_address(peer, &parts.headers)
+}
+
+/// Extractor counting order lookups against LOOKUP_RATE_LIMIT, so order IDs and dates can not be guessed by brute force
+pub struct RateLimit;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RateLimit
+    where
+        AppState: FromRef<S>,
+        S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let client = request_client(parts);
+        let Err(retry_after) = AppState::from_ref(state).lookup_limiter.check(client) else {
+            return Ok(RateLimit);
+        };
+        info!("Lookup rate limit reached, rejecting {}.", parts.uri.path());
+        let retry_after = [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())];
+        if parts.uri.path().starts_with("/api/") {
+            let error = ApiError { error: "Too many lookups, please try again later.".to_string() };
+            return Err((StatusCode::TOO_MANY_REQUESTS, retry_after, Json(error)).into_response());
+        }
+        let Ok(Lang(locale)) = Lang::from_request_parts(parts, state).await;
+        Err((retry_after, error_page(StatusCode::TOO_MANY_REQUESTS, locale, None)).into_response())
+    }
+}
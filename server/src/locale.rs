@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::convert::Infallible;
+use axum::{
+    async_trait, extract::{FromRequestParts, Path}, http::{header, request::Parts, HeaderMap, StatusCode}, response::{IntoResponse, Response}, routing::get, Router
+};
+use cs_data::i18n::Locale;
+use crate::{errors::error_page, AppState};
+
+/// Name of the query parameter and cookie selecting the language
+const LANG: &str = "lang";
+
+/// Keep the language choice for a year
+const COOKIE_MAX_AGE: u32 = 365 * 24 * 60 * 60;
+
+/// Extractor for the language of the page, in order of precedence from the
+/// `lang` query parameter, the `lang` cookie and the Accept-Language header
+pub struct Lang(pub Locale);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Lang
+    where
+        S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let from_query = parts.uri.query()
+            .and_then(|q| find_pair(q.split('&'), LANG))
+            .and_then(Locale::from_tag);
+        let from_cookie = || parts.headers.get_all(header::COOKIE).iter()
+            .filter_map(|v| v.to_str().ok())
+            .find_map(|v| find_pair(v.split(';'), LANG))
+            .and_then(Locale::from_tag);
+        let from_header = || parts.headers.get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(Locale::negotiate);
+        Ok(Lang(from_query.or_else(from_cookie).or_else(from_header).unwrap_or_default()))
+    }
+}
+
+/// Value of the first `name=value` pair with the given name
+fn find_pair<'a>(mut pairs: impl Iterator<Item = &'a str>, name: &str) -> Option<&'a str> {
+    pairs.find_map(|pair| {
+        let (key, value) = pair.trim().split_once('=')?;
+        (key == name).then_some(value)
+    })
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/lang/:code", get(set_language))
+}
+
+/// Remember the selected language in a cookie and go back to the page the switch was clicked on
+async fn set_language(Path(code): Path<String>, Lang(current): Lang, headers: HeaderMap) -> Response {
+    let Some(locale) = Locale::from_tag(&code) else {
+        return error_page(StatusCode::NOT_FOUND, current, None);
+    };
+    (
+        StatusCode::SEE_OTHER,
+        [
+            (header::SET_COOKIE, format!("{}={}; Path=/; Max-Age={}; SameSite=Lax", LANG, locale.code(), COOKIE_MAX_AGE)),
+            (header::LOCATION, back_location(&headers)),
+        ],
+    )
+        .into_response()
+}
+
+/// Local path of the referring page, so the redirect can not be used to send visitors elsewhere,
+/// a `lang` query parameter is dropped as it would override the new choice
+fn back_location(headers: &HeaderMap) -> String {
+    let Some(uri) = headers.get(header::REFERER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<axum::http::Uri>().ok())
+    else {
+        return "/".to_string();
+    };
+    let path = uri.path();
+    if !path.starts_with('/') || path.starts_with("//") {
+        return "/".to_string();
+    }
+    let query: Vec<&str> = uri.query().unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && find_pair([*pair].into_iter(), LANG).is_none())
+        .collect();
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query.join("&"))
+    }
+}
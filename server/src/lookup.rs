@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use axum::{
+    async_trait, extract::{FromRef, FromRequest, Query, Request}, http::{Method, StatusCode}, response::{IntoResponse, Response}, Form, Json
+};
+use chrono::NaiveDate;
+use cs_data::{glasgow_data, i18n::Locale};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use crate::auth::constant_time_eq;
+use crate::privacy::Redactor;
+use crate::{ApiError, AppState};
+
+/// Order lookup, the order is verified either by its date or the backer email address
+pub struct OrderQuery {
+    pub id: usize,
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+    pub email: Option<String>,
+}
+
+/// Lookup form fields as submitted, kept to fill in the form again
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LookupForm {
+    pub id: String,
+    pub year: String,
+    pub month: String,
+    pub day: String,
+    pub email: String,
+}
+
+impl LookupForm {
+    /// Check the fields, returning the message keys of all problems found
+    pub fn validate(&self, email_verification: bool) -> Result<OrderQuery, Vec<&'static str>> {
+        let mut errors = Vec::new();
+        let id = match self.id.trim() {
+            "" => {
+                errors.push("lookup.id_missing");
+                None
+            }
+            id => id.parse().map_err(|_| errors.push("lookup.id_invalid")).ok()
+        };
+
+        let email = Some(self.email.trim()).filter(|e| !e.is_empty()).map(str::to_string);
+        let by_email = email_verification && email.is_some();
+        let fields = [&self.year, &self.month, &self.day].map(|f| f.trim());
+        let mut date = (None, None, None);
+        if by_email {
+            // The date is not checked when looking up by email
+        } else if fields.iter().all(|f| f.is_empty()) {
+            errors.push(if email_verification { "lookup.date_or_email_missing" } else { "lookup.date_missing" });
+        } else if fields.iter().any(|f| f.is_empty()) {
+            errors.push("lookup.date_incomplete");
+        } else {
+            let year = fields[0].parse::<i32>().ok().map(|year| if year < 100 { year + 2000 } else { year });
+            let month = fields[1].parse::<u32>().ok();
+            let day = fields[2].parse::<u32>().ok();
+            match (year, month, day) {
+                (Some(y), Some(m), Some(d)) if NaiveDate::from_ymd_opt(y, m, d).is_some() => date = (year, month, day),
+                _ => errors.push("lookup.date_invalid")
+            }
+        }
+
+        match id {
+            Some(id) if errors.is_empty() => Ok(OrderQuery { id, year: date.0, month: date.1, day: date.2, email }),
+            _ => Err(errors)
+        }
+    }
+}
+
+/// Extractor for a validated order lookup, from the form body of a POST or the query string of a GET
+pub struct OrderLookup {
+    pub query: OrderQuery,
+    pub form: LookupForm,
+}
+
+/// Lookup that did not pass validation, pages show the form again with the messages
+pub struct LookupError {
+    pub form: LookupForm,
+    /// Message catalog keys
+    pub errors: Vec<&'static str>,
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for OrderLookup
+    where
+        AppState: FromRef<S>,
+        S: Send + Sync,
+{
+    type Rejection = LookupError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let email_verification = AppState::from_ref(state).config.email_verification;
+        // All fields are strings, so this only fails on a malformed body or query string
+        let form = if req.method() == Method::POST {
+            Form::<LookupForm>::from_request(req, state).await.map(|Form(form)| form).unwrap_or_default()
+        } else {
+            // The email is only taken from a POST body, so it does not end up in URLs, browser history and access logs
+            Query::<LookupForm>::try_from_uri(req.uri())
+                .map(|Query(form)| LookupForm { email: String::new(), ..form })
+                .unwrap_or_default()
+        };
+        match form.validate(email_verification) {
+            Ok(query) => Ok(OrderLookup { query, form }),
+            Err(errors) => Err(LookupError { form, errors }),
+        }
+    }
+}
+
+/// Used as is by the JSON API
+impl IntoResponse for LookupError {
+    fn into_response(self) -> Response {
+        let locale = Locale::default();
+        let error = self.errors.iter().map(|key| locale.tr(key)).collect::<Vec<_>>().join(" ");
+        (StatusCode::BAD_REQUEST, Json(ApiError { error })).into_response()
+    }
+}
+
+/// Hash an email address the same way as the Email Hash column of the order export
+pub fn hash_email(email: &str) -> String {
+    let digest = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl OrderQuery {
+    fn date_matches(&self, order: &glasgow_data::Order) -> bool {
+        let query_date = match (self.year, self.month, self.day) {
+            (Some(year), Some(month), Some(day)) => NaiveDate::from_ymd_opt(year, month, day),
+            _ => None
+        };
+        match query_date {
+            Some(query_date) => order.date.signed_duration_since(query_date).abs().num_days() <= 1,
+            None => false
+        }
+    }
+
+    fn email_matches(&self, order: &glasgow_data::Order) -> bool {
+        match (&self.email, &order.email_hash) {
+            (Some(email), Some(email_hash)) => constant_time_eq(hash_email(email).as_bytes(), email_hash.trim().to_lowercase().as_bytes()),
+            _ => false
+        }
+    }
+
+    /// Look up the order, only returning it if the order date or email matches the query
+    pub fn verify(&self, orders: &glasgow_data::Orders, email_verification: bool, redactor: &Redactor) -> Option<glasgow_data::Order> {
+        // The order date and email act as the password, so they never go into the log
+        let logged_id = redactor.order_id(self.id);
+        let Some(order) = orders.get_order(self.id).cloned() else {
+            info!("Query of order id {}. -> Invalid", logged_id);
+            return None;
+        };
+        if email_verification && self.email.is_some() {
+            if self.email_matches(&order) {
+                info!("Query of order id {}. -> Valid (email)", logged_id);
+                return Some(order);
+            }
+            info!("Query of order id {}. -> Email mismatch", logged_id);
+            return None;
+        }
+        if self.date_matches(&order) {
+            info!("Query of order id {}. -> Valid", logged_id);
+            Some(order)
+        } else {
+            info!("Query of order id {}. -> Date mismatch", logged_id);
+            None
+        }
+    }
+}
@@ -5,133 +5,356 @@
 use anyhow::Context;
 use askama::Template;
 use axum::{
-    extract::{Query, State}, http::StatusCode, response::{Html, IntoResponse, Response}, routing::get, Router
+    extract::{Path, State}, http::StatusCode, response::{Html, IntoResponse, Response}, routing::get, Json, Router
 };
-use axum_server;
-use serde::Deserialize;
+use serde::Serialize;
+use tower_http::compression::CompressionLayer;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use cs_data::glasgow_data;
-use chrono::NaiveDate;
+use cs_data::{glasgow_data, i18n::Locale};
+use std::{net::SocketAddr, sync::{Arc, RwLock}, time::Duration};
+use config::Config;
+use feed::Feed;
+use health::Health;
+use locale::Lang;
+use limit::{RateLimit, RateLimiter};
+use lookup::{LookupError, LookupForm, OrderLookup};
+use notify::Notifier;
+use privacy::Redactor;
+use subscriptions::Subscriptions;
+use token::LinkSigner;
+
+mod admin;
+mod assets;
+mod auth;
+mod charts;
+mod config;
+mod embed;
+mod errors;
+mod feed;
+mod health;
+mod lifecycle;
+mod limit;
+mod locale;
+mod lookup;
+mod notify;
+mod persist;
+mod privacy;
+mod reload;
+mod subscriptions;
+#[cfg(test)]
+mod tests;
+mod token;
+
+/// Shared state handed to all request handlers
+#[derive(Clone)]
+pub struct AppState {
+    /// Replaced as a whole on every reload, handlers work on a snapshot
+    orders: Arc<RwLock<Arc<glasgow_data::Orders>>>,
+    pub config: Arc<Config>,
+    pub link_signer: Option<LinkSigner>,
+    /// Signs the order ID into the subscription form of an order page, so the form does not carry the lookup fields
+    pub form_signer: LinkSigner,
+    pub redactor: Redactor,
+    pub subscriptions: Arc<Subscriptions>,
+    pub notifier: Arc<Notifier>,
+    pub feed: Arc<Feed>,
+    pub lookup_limiter: Arc<RateLimiter>,
+    pub admin_limiter: Arc<RateLimiter>,
+    pub health: Arc<Health>,
+}
+
+impl AppState {
+    /// Snapshot of the currently loaded data
+    pub fn orders(&self) -> Arc<glasgow_data::Orders> {
+        self.orders.read().unwrap().clone()
+    }
+
+    /// Set up the shared state around the initially loaded data
+    pub fn new(config: Config, orders: glasgow_data::Orders) -> anyhow::Result<Self> {
+        let subscriptions = Subscriptions::load(config.subscriptions_data.clone())?;
+        let redactor = Redactor::new(config.log_redaction, config.log_salt.as_deref());
+        let notifier = Notifier::from_config(&config, redactor.clone())?;
+        let feed = Feed::load(config.feed_data.clone())?;
+        Ok(Self {
+            orders: Arc::new(RwLock::new(Arc::new(orders))),
+            link_signer: config.link_secret.as_deref().map(LinkSigner::new),
+            form_signer: LinkSigner::random(),
+            redactor,
+            subscriptions: Arc::new(subscriptions),
+            notifier: Arc::new(notifier),
+            feed: Arc::new(feed),
+            health: Arc::new(Health::new()),
+            lookup_limiter: Arc::new(RateLimiter::new(config.lookup_rate_limit, Duration::from_secs(60))),
+            admin_limiter: Arc::new(RateLimiter::new(auth::FAILED_LOGIN_LIMIT, auth::FAILED_LOGIN_WINDOW)),
+            config: Arc::new(config),
+        })
+    }
+
+    /// Swap in freshly loaded data and return the previous data
+    pub fn replace_orders(&self, orders: Arc<glasgow_data::Orders>) -> Arc<glasgow_data::Orders> {
+        std::mem::replace(&mut *self.orders.write().unwrap(), orders)
+    }
+}
+
+/// All routes of the site
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(index_page))
+        .route("/order", get(order_page).post(order_page))
+        .route("/shipments", get(shipments_page))
+        .route("/status/:token", get(status_page))
+        .route("/api/order", get(order_api).post(order_api))
+        .route("/api/status/:token", get(status_api))
+        .merge(health::router())
+        .merge(locale::router())
+        .merge(admin::router())
+        .merge(subscriptions::router())
+        .merge(feed::router())
+        .merge(embed::router())
+        .merge(charts::router())
+        .merge(assets::router())
+        .fallback(errors::not_found)
+        .with_state(state)
+        .layer(CompressionLayer::new())
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let config = Config::from_env()?;
+
+    // Old log files beyond the retention count are deleted by the appender on rotation
+    let file_layer = match &config.log_dir {
+        Some(log_dir) => {
+            let appender = tracing_appender::rolling::Builder::new()
+                .rotation(tracing_appender::rolling::Rotation::DAILY)
+                .filename_prefix("server.log")
+                .max_log_files(config.log_retention_days.max(1))
+                .build(log_dir)
+                .context("could not open the log directory")?;
+            Some(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(appender))
+        }
+        None => None
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "server=debug".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(file_layer)
         .init();
 
     info!("reading and analyzing data...");
 
-    let fulfillment_data = std::env::var("FULFILLMENT_DATA").unwrap();
-    let production_data = std::env::var("PRODUCTION_DATA").unwrap();
+    info!("Fulfillment data path: {:?}", config.fulfillment_data);
+    info!("Production data path: {:?}", config.production_data);
 
-    info!("Fulfillment data path: {:?}", fulfillment_data);
-    info!("Production data path: {:?}", production_data);
-
-    let mut orders = glasgow_data::Orders::new(&fulfillment_data, &production_data).unwrap();
-    orders.calculate_queue();
+    if let Some(status_overrides) = &config.status_overrides {
+        info!("Status overrides path: {:?}", status_overrides);
+    }
+    if let Some(returns_data) = &config.returns_data {
+        info!("Returns data path: {:?}", returns_data);
+    }
+    if let Some(purchase_order_data) = &config.purchase_order_data {
+        info!("Purchase order data path: {:?}", purchase_order_data);
+    }
+    let orders = reload::load_orders(&config)?;
     orders.print_stats();
 
     info!("initializing router...");
 
-    let router = Router::new()
-        .route("/", get(index_page))
-        .route("/order", get(order_page))
-        .with_state(orders);
-    let port = 8019_u16;
-    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
-
-    info!("router initialized, now listening on port {}", port);
+    if config.link_secret.is_none() {
+        info!("LINK_SECRET is not set, shareable order links are disabled.");
+    }
+    assets::init(config.static_dir.as_deref())?;
+    let state = AppState::new(config, orders)?;
+    match state.config.reload_interval {
+        Some(seconds) => {
+            info!("Checking for changed input data every {} seconds.", seconds);
+            tokio::spawn(reload::reload_task(state.clone(), Duration::from_secs(seconds.max(1))));
+        }
+        None => info!("RELOAD_INTERVAL is not set, the data is only loaded once."),
+    }
+    let shutdown_timeout = Duration::from_secs(state.config.shutdown_timeout);
+    let router = app(state);
+    let server = match lifecycle::inherited_listener()? {
+        Some(listener) => {
+            info!("router initialized, now listening on the inherited socket {}", listener.local_addr()?);
+            axum_server::from_tcp(listener)
+        }
+        None => {
+            let port = 8019_u16;
+            let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+            info!("router initialized, now listening on port {}", port);
+            axum_server::bind(addr)
+        }
+    };
 
-    axum_server::Server::bind(addr)
-        .serve(router.into_make_service())
+    let handle = axum_server::Handle::new();
+    tokio::spawn(lifecycle::graceful_shutdown(handle.clone(), shutdown_timeout));
+    server
+        .handle(handle)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .context("error while starting server")?;
 
+    info!("Server stopped.");
     Ok(())
 }
 
-async fn index_page(State(orders): State<glasgow_data::Orders>) -> impl IntoResponse {
+async fn index_page(State(state): State<AppState>, Lang(locale): Lang) -> impl IntoResponse {
     info!("Index page call.");
     let template =
-        IndexTemplate {orders};
+        IndexTemplate {orders: state.orders(), email_verification: state.config.email_verification, locale};
     HtmlTemplate(template)
 }
 
 #[derive(Template)]
 #[template(path = "index.html")]
 struct IndexTemplate {
-    orders: glasgow_data::Orders
+    orders: Arc<glasgow_data::Orders>,
+    email_verification: bool,
+    locale: Locale
 }
 
-#[derive(Deserialize)]
-struct OrderQuery {
-    id: usize,
-    year: i32,
-    month: u32,
-    day: u32,
+async fn shipments_page(State(state): State<AppState>) -> impl IntoResponse {
+    info!("Shipments page call.");
+    HtmlTemplate(ShipmentsTemplate {orders: state.orders()})
 }
 
-async fn order_page(State(orders): State<glasgow_data::Orders>, Query(order_query): Query<OrderQuery>) -> impl IntoResponse {
-    let mut order = orders.get_order(order_query.id).cloned();
-    let oq = OrderQuery {
-        id: order_query.id,
-        year: if order_query.year < 100 { order_query.year + 2000 } else { order_query.year },
-        month: order_query.month,
-        day: order_query.day,
+#[derive(Template)]
+#[template(path = "shipments.html")]
+struct ShipmentsTemplate {
+    orders: Arc<glasgow_data::Orders>
+}
+
+async fn order_page(
+    State(state): State<AppState>,
+    _: RateLimit,
+    Lang(locale): Lang,
+    lookup: Result<OrderLookup, LookupError>,
+) -> impl IntoResponse {
+    let orders = state.orders();
+    let (form, errors, order) = match lookup {
+        Ok(OrderLookup { query, form }) => {
+            let order = query.verify(&orders, state.config.email_verification, &state.redactor);
+            (form, Vec::new(), order)
+        }
+        Err(LookupError { form, errors }) => (form, errors, None)
     };
-    let query_date = NaiveDate::from_ymd_opt(oq.year, oq.month, oq.day);
-    if order.is_some() {
-        info!("Query of order id {} with date {}-{}-{}. -> Valid (Date {}) https://glasgow.1bitsquared.com/order?id={}&year={}&month={}&day={}",
-            oq.id, oq.year, oq.month, oq.day,
-            order.clone().unwrap().date,
-            oq.id, oq.year, oq.month, oq.day
-        );
+    let status = if !errors.is_empty() {
+        StatusCode::BAD_REQUEST
+    } else if order.is_none() {
+        StatusCode::NOT_FOUND
     } else {
-        info!("Query of order id {} with date {}-{}-{}. -> Invalid", oq.id, oq.year, oq.month, oq.day);
-    }
-    order = if order.is_some() && query_date.is_some() {
-            let od = order.unwrap();
-            let diff = od.date.signed_duration_since(query_date.unwrap()).abs().num_days();
-            if diff > 1 {
-                None
-            } else {
-                Some(od)
-            }
-        } else {
-            None
-        };
-    if order.is_none() {
-        info!("Query failed due to date mismatch.");
-    }
+        StatusCode::OK
+    };
+    let share_token = order.as_ref().and_then(|o| state.link_signer.as_ref().map(|s| s.sign(o.cs_id)));
+    let subscribe_token = order.as_ref().map(|o| state.form_signer.sign(o.cs_id));
     let template =
         OrderTemplate {
             orders,
-            order_id: oq.id,
-            order_year: oq.year,
-            order_month: oq.month,
-            order_day: oq.day,
-            order};
-    HtmlTemplate(template)
+            email_verification: state.config.email_verification,
+            form,
+            errors,
+            show_query: true,
+            share_token,
+            subscribe_token,
+            order,
+            locale};
+    (status, HtmlTemplate(template))
+}
+
+/// Look up the order of a signed status link
+fn verify_token(state: &AppState, token: &str) -> Option<glasgow_data::Order> {
+    let order_id = state.link_signer.as_ref()?.verify(token);
+    match order_id {
+        Some(order_id) => info!("Status link query of order id {}. -> Valid", state.redactor.order_id(order_id)),
+        None => info!("Status link query. -> Invalid token"),
+    }
+    state.orders().get_order(order_id?).cloned()
+}
+
+/// Order page addressed by a signed status link instead of the order ID and date
+async fn status_page(State(state): State<AppState>, _: RateLimit, Lang(locale): Lang, Path(token): Path<String>) -> impl IntoResponse {
+    let order = verify_token(&state, &token);
+    let status = if order.is_some() { StatusCode::OK } else { StatusCode::NOT_FOUND };
+    let template =
+        OrderTemplate {
+            orders: state.orders(),
+            email_verification: state.config.email_verification,
+            form: LookupForm::default(),
+            errors: Vec::new(),
+            show_query: false,
+            subscribe_token: order.as_ref().map(|o| state.form_signer.sign(o.cs_id)),
+            share_token: order.as_ref().map(|_| token),
+            order,
+            locale};
+    (status, HtmlTemplate(template))
+}
+
+#[derive(Serialize)]
+struct ApiError {
+    error: String
+}
+
+/// JSON version of the order page, including line item shipping and tracking state
+async fn order_api(State(state): State<AppState>, _: RateLimit, OrderLookup { query: oq, .. }: OrderLookup) -> Response {
+    match oq.verify(&state.orders(), state.config.email_verification, &state.redactor) {
+        Some(order) => Json(order).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError { error: format!("Could not find order with the ID {}.", oq.id) }),
+        )
+            .into_response(),
+    }
+}
+
+/// Order data safe to hand to anybody holding a status link, leaves out the order date and personal details
+#[derive(Serialize)]
+struct SharedOrder {
+    cs_id: usize,
+    queue_id: usize,
+    status: glasgow_data::OrderStatus,
+    items: Vec<glasgow_data::LineItem>,
+}
+
+async fn status_api(State(state): State<AppState>, _: RateLimit, Path(token): Path<String>) -> Response {
+    match verify_token(&state, &token) {
+        Some(order) => Json(SharedOrder {
+            cs_id: order.cs_id,
+            queue_id: order.queue_id,
+            status: order.status,
+            items: order.items,
+        }).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError { error: "Invalid order status link.".to_string() }),
+        )
+            .into_response(),
+    }
 }
 
 #[derive(Template)]
 #[template(path = "order.html")]
 struct OrderTemplate {
-    orders: glasgow_data::Orders,
-    order_id: usize,
-    order_year: i32,
-    order_month: u32,
-    order_day: u32,
-    order: Option<glasgow_data::Order>
+    orders: Arc<glasgow_data::Orders>,
+    email_verification: bool,
+    /// Submitted lookup, fills in the form again
+    form: LookupForm,
+    /// Message keys of lookup validation problems
+    errors: Vec<&'static str>,
+    /// Lookup form result, status links show a different message as the date is the secret
+    show_query: bool,
+    share_token: Option<String>,
+    subscribe_token: Option<String>,
+    order: Option<glasgow_data::Order>,
+    locale: Locale
 }
 
 /// A wrapper type that we'll use to encapsulate HTML parsed by askama into valid HTML for axum to serve.
-struct HtmlTemplate<T>(T);
+pub struct HtmlTemplate<T>(pub T);
 
 /// Allows us to convert Askama HTML templates into valid HTML for axum to serve in the response.
 impl<T> IntoResponse for HtmlTemplate<T>
@@ -143,12 +366,8 @@ impl<T> IntoResponse for HtmlTemplate<T>
         match self.0.render() {
             // If we're able to successfully parse and aggregate the template, serve it
             Ok(html) => Html(html).into_response(),
-            // If we're not, return an error or some bit of fallback HTML
-            Err(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to render template. Error: {}", err),
-            )
-                .into_response(),
+            // If we're not, log the error and show the error page
+            Err(err) => errors::render_error(err),
         }
     }
 }
\ No newline at end of file
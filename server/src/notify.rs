@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{io::Write, net::{IpAddr, SocketAddr}, time::Duration};
+use anyhow::Context;
+use axum::async_trait;
+use cs_data::changes::OrderChange;
+use lettre::{message::Mailbox, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::{config::Config, privacy::Redactor};
+
+/// Where the notifications of a subscription are delivered to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "address", rename_all = "lowercase")]
+pub enum Target {
+    Email(String),
+    Webhook(String)
+}
+
+impl Target {
+    /// Accept https URLs as webhooks and anything else that looks like an address as email
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.starts_with("https://") && !s.contains(char::is_whitespace) {
+            Some(Target::Webhook(s.to_string()))
+        } else if s.parse::<Mailbox>().is_ok() && s.contains('@') {
+            Some(Target::Email(s.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Kind of target for the log, the address itself is personal data and webhook URLs often contain a secret
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Email(_) => "email",
+            Target::Webhook(_) => "webhook"
+        }
+    }
+}
+
+/// Message about a change of one order, sent to each of its subscribers
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    #[serde(skip)]
+    pub order_id: usize,
+    pub subject: String,
+    pub text: String,
+    /// Not set for the confirmation of a new subscription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<OrderChange>,
+    pub unsubscribe_url: String
+}
+
+impl Notification {
+    pub fn new(change: &OrderChange, unsubscribe_url: String) -> Self {
+        Self {
+            order_id: change.order_id,
+            subject: format!("Glasgow order {} update", change.order_id),
+            text: format!("{}\n\nTo stop receiving these updates open {}\n", change, unsubscribe_url),
+            change: Some(change.clone()),
+            unsubscribe_url
+        }
+    }
+
+    /// Asks the owner of an email address to confirm a subscription before any updates are sent
+    pub fn confirmation(order_id: usize, confirm_url: String, unsubscribe_url: String) -> Self {
+        Self {
+            order_id,
+            subject: format!("Confirm updates for Glasgow order {}", order_id),
+            text: format!("Someone asked for updates about Glasgow order {} to be sent to this address.\n\n\
+                To receive them open {}\n\nIf this was not you, ignore this message or open {}\n", order_id, confirm_url, unsubscribe_url),
+            change: None,
+            unsubscribe_url
+        }
+    }
+}
+
+/// Addresses on the local machine or network, webhooks must not reach them
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+            || ip.is_broadcast() || ip.is_multicast()
+            // Carrier grade NAT, 100.64.0.0/10
+            || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => ip.is_loopback() || ip.is_unspecified() || ip.is_multicast()
+                || ip.is_unique_local() || ip.is_unicast_link_local()
+        }
+    }
+}
+
+/// Parse a webhook URL and resolve its host, refusing hosts that resolve to internal addresses
+pub async fn resolve_webhook(url: &str) -> anyhow::Result<(reqwest::Url, SocketAddr)> {
+    let url = reqwest::Url::parse(url).context("invalid webhook URL")?;
+    anyhow::ensure!(url.scheme() == "https", "webhooks need an https URL");
+    let host = url.host_str().context("webhook URL without a host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port)).await
+        .context("could not resolve the webhook host")?
+        .collect();
+    if let Some(addr) = addrs.iter().find(|a| is_internal(a.ip())) {
+        anyhow::bail!("webhook host resolves to the internal address {}", addr.ip());
+    }
+    let addr = *addrs.first().context("webhook host has no addresses")?;
+    Ok((url, addr))
+}
+
+/// Delivery channel for notifications
+#[async_trait]
+pub trait Sender: Send + Sync {
+    /// Whether this sender is able to deliver to the target
+    fn handles(&self, target: &Target) -> bool;
+
+    async fn send(&self, target: &Target, notification: &Notification) -> anyhow::Result<()>;
+}
+
+/// Appends all notifications as JSON lines to a local file instead of sending them
+pub struct FileSender {
+    path: String
+}
+
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    target: &'a Target,
+    #[serde(flatten)]
+    notification: &'a Notification
+}
+
+#[async_trait]
+impl Sender for FileSender {
+    fn handles(&self, _target: &Target) -> bool {
+        true
+    }
+
+    async fn send(&self, target: &Target, notification: &Notification) -> anyhow::Result<()> {
+        let line = serde_json::to_string(&FileEntry { target, notification })?;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)
+            .with_context(|| format!("could not open {}", self.path))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Sends email notifications through an SMTP server
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox
+}
+
+#[async_trait]
+impl Sender for SmtpSender {
+    fn handles(&self, target: &Target) -> bool {
+        matches!(target, Target::Email(_))
+    }
+
+    async fn send(&self, target: &Target, notification: &Notification) -> anyhow::Result<()> {
+        let Target::Email(address) = target else {
+            anyhow::bail!("SMTP can only deliver to email addresses");
+        };
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(address.parse()?)
+            .subject(&notification.subject)
+            .body(notification.text.clone())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Posts the notification as JSON to a backer provided URL
+pub struct WebhookSender;
+
+#[async_trait]
+impl Sender for WebhookSender {
+    fn handles(&self, target: &Target) -> bool {
+        matches!(target, Target::Webhook(_))
+    }
+
+    async fn send(&self, target: &Target, notification: &Notification) -> anyhow::Result<()> {
+        let Target::Webhook(url) = target else {
+            anyhow::bail!("Webhooks can only deliver to URLs");
+        };
+        let (url, addr) = resolve_webhook(url).await?;
+        // Connect to the address that was checked, a second lookup could return a different one,
+        // redirects are not followed as they could point anywhere
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(url.host_str().unwrap_or_default(), addr)
+            .build()?;
+        // reqwest errors include the URL, which must not end up in the log
+        client.post(url).json(notification).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.without_url())?;
+        Ok(())
+    }
+}
+
+/// All configured senders, a target is delivered by the first sender handling it
+pub struct Notifier {
+    senders: Vec<Box<dyn Sender>>,
+    redactor: Redactor
+}
+
+impl Notifier {
+    /// NOTIFY_FILE replaces all other senders, so nothing goes out while testing
+    pub fn from_config(config: &Config, redactor: Redactor) -> anyhow::Result<Self> {
+        let mut senders: Vec<Box<dyn Sender>> = Vec::new();
+        if let Some(path) = &config.notify_file {
+            info!("Notifications are written to {:?}", path);
+            senders.push(Box::new(FileSender { path: path.clone() }));
+            return Ok(Self { senders, redactor });
+        }
+        if let Some(smtp_url) = &config.smtp_url {
+            let from = config.smtp_from.as_deref().context("SMTP_FROM is needed for email notifications")?;
+            senders.push(Box::new(SmtpSender {
+                transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url).context("SMTP_URL is invalid")?.build(),
+                from: from.parse().context("SMTP_FROM is not a valid address")?
+            }));
+        } else {
+            info!("SMTP_URL is not set, email notifications are disabled.");
+        }
+        senders.push(Box::new(WebhookSender));
+        Ok(Self { senders, redactor })
+    }
+
+    pub fn accepts(&self, target: &Target) -> bool {
+        self.senders.iter().any(|s| s.handles(target))
+    }
+
+    /// Deliver a notification, failures are only logged so one broken target does not block the others
+    pub async fn send(&self, target: &Target, notification: &Notification) {
+        let Some(sender) = self.senders.iter().find(|s| s.handles(target)) else {
+            return;
+        };
+        if let Err(err) = sender.send(target, notification).await {
+            warn!("Failed to deliver {} notification for order id {}: {:#}",
+                target.kind(), self.redactor.order_id(notification.order_id), err);
+        }
+    }
+}
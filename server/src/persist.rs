@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Read server side state from a JSON file, a missing file is an empty state
+pub fn load_json<T: DeserializeOwned + Default>(path: Option<&str>) -> anyhow::Result<T> {
+    match path {
+        Some(path) if std::path::Path::new(path).exists() => {
+            let data = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path))?;
+            serde_json::from_str(&data).with_context(|| format!("could not parse {}", path))
+        }
+        _ => Ok(T::default())
+    }
+}
+
+/// Write to a temporary file first, so a crash never leaves a truncated file behind
+pub fn save_json<T: Serialize + ?Sized>(path: Option<&str>, value: &T) -> anyhow::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
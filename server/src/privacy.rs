@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Redacts personal data before it ends up in the server log
+#[derive(Clone)]
+pub struct Redactor {
+    /// Key for hashing order IDs, no redaction when not set
+    key: Option<Vec<u8>>
+}
+
+impl Redactor {
+    /// Without a salt a random per process key is used, so hashes can not be correlated across restarts
+    pub fn new(enabled: bool, salt: Option<&str>) -> Self {
+        let key = enabled.then(|| match salt {
+            Some(salt) => salt.as_bytes().to_vec(),
+            None => rand::random::<[u8; 32]>().to_vec()
+        });
+        Self {
+            key
+        }
+    }
+
+    /// Order ID as it should appear in the log
+    pub fn order_id(&self, order_id: usize) -> String {
+        let Some(key) = &self.key else {
+            return order_id.to_string();
+        };
+        // Plain hashes of the small order ID space could be reversed by trying all IDs, so use a keyed hash
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(&(order_id as u64).to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hash: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("#{}", hash)
+    }
+}
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::{sync::Arc, time::{Duration, SystemTime}};
+use anyhow::anyhow;
+use cs_data::glasgow_data::Orders;
+use tracing::{info, warn};
+use crate::{config::Config, AppState};
+
+/// Load the order data together with all configured optional inputs
+pub fn load_orders(config: &Config) -> anyhow::Result<Orders> {
+    // The cs-data errors are not Send, turn them into plain messages
+    let mut orders = Orders::new(&config.fulfillment_data, &config.production_data)
+        .map_err(|e| anyhow!("could not load the order data: {}", e))?;
+    orders.calculate_queue();
+    if let Some(status_overrides) = &config.status_overrides {
+        orders.load_status_overrides(status_overrides).map_err(|e| anyhow!("could not load the status overrides: {}", e))?;
+    }
+    if let Some(returns_data) = &config.returns_data {
+        orders.load_returns(returns_data).map_err(|e| anyhow!("could not load the returns data: {}", e))?;
+    }
+    if let Some(purchase_order_data) = &config.purchase_order_data {
+        orders.load_purchase_orders(purchase_order_data).map_err(|e| anyhow!("could not load the purchase order data: {}", e))?;
+    }
+    Ok(orders)
+}
+
+/// Modification times of all input files
+fn input_mtimes(config: &Config) -> Vec<Option<SystemTime>> {
+    [Some(&config.fulfillment_data), Some(&config.production_data), config.status_overrides.as_ref(),
+        config.returns_data.as_ref(), config.purchase_order_data.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Load the data from the input files without blocking the server
+pub async fn load(config: Arc<Config>) -> anyhow::Result<Orders> {
+    tokio::task::spawn_blocking(move || load_orders(&config)).await?
+}
+
+/// Swap in freshly loaded data, publish the progress and notify subscribers about the changed orders,
+/// returns the number of changed orders
+pub async fn publish(state: &AppState, orders: Orders) -> usize {
+    let orders = Arc::new(orders);
+    let previous = state.replace_orders(orders.clone());
+    state.health.reloaded();
+    let changes = orders.changes_since(&previous);
+    info!("Reload done, {} orders changed.", changes.len());
+    if let Err(err) = state.feed.record(&orders, orders.update_since(&previous)) {
+        warn!("Failed to store the feed: {:#}", err);
+    }
+    state.subscriptions.notify(&state.notifier, &changes, &state.config.public_url).await;
+    changes.len()
+}
+
+/// Reload the data whenever one of the input files changes
+pub async fn reload_task(state: AppState, interval: Duration) {
+    let mut mtimes = input_mtimes(&state.config);
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes right away, the data was just loaded
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let current = input_mtimes(&state.config);
+        if current == mtimes {
+            continue;
+        }
+        mtimes = current;
+        info!("Input data changed, reloading...");
+        match load(state.config.clone()).await {
+            Ok(orders) => {
+                publish(&state, orders).await;
+            }
+            Err(err) => {
+                warn!("Reload failed, keeping the previous data: {:#}", err);
+                state.health.reload_failed(format!("{:#}", err));
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use std::sync::Mutex;
+use askama::Template;
+use axum::{extract::{rejection::FormRejection, Path, State}, http::StatusCode, response::IntoResponse, routing::{get, post}, Form, Router};
+use cs_data::changes::OrderChange;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::{limit::RateLimit, notify::{resolve_webhook, Notification, Notifier, Target}, persist::{load_json, save_json}, AppState, HtmlTemplate};
+
+/// Upper limit of subscriptions per order, so the form can not be used to spam
+const MAX_SUBSCRIPTIONS_PER_ORDER: usize = 5;
+
+/// Backer request to be notified about changes of an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Random ID, also used as the unsubscribe token
+    pub id: String,
+    pub order_id: usize,
+    pub target: Target,
+    /// Email subscriptions only receive updates once the link in the confirmation email was opened
+    #[serde(default)]
+    pub confirmed: bool
+}
+
+pub enum AddResult {
+    Added(Subscription),
+    AlreadySubscribed,
+    LimitReached
+}
+
+/// All subscriptions, persisted as JSON when SUBSCRIPTIONS_DATA is set
+pub struct Subscriptions {
+    path: Option<String>,
+    list: Mutex<Vec<Subscription>>
+}
+
+impl Subscriptions {
+    pub fn load(path: Option<String>) -> anyhow::Result<Self> {
+        let list = load_json(path.as_deref())?;
+        Ok(Self {
+            path,
+            list: Mutex::new(list)
+        })
+    }
+
+    pub fn add(&self, order_id: usize, target: Target) -> anyhow::Result<AddResult> {
+        let mut list = self.list.lock().unwrap();
+        let existing: Vec<&Subscription> = list.iter().filter(|s| s.order_id == order_id).collect();
+        if existing.iter().any(|s| s.target == target) {
+            return Ok(AddResult::AlreadySubscribed);
+        }
+        if existing.len() >= MAX_SUBSCRIPTIONS_PER_ORDER {
+            return Ok(AddResult::LimitReached);
+        }
+        let id: String = rand::random::<[u8; 16]>().iter().map(|b| format!("{:02x}", b)).collect();
+        let confirmed = !matches!(target, Target::Email(_));
+        let subscription = Subscription { id, order_id, target, confirmed };
+        list.push(subscription.clone());
+        save_json(self.path.as_deref(), &*list)?;
+        Ok(AddResult::Added(subscription))
+    }
+
+    /// Returns false if there was no subscription with this ID
+    pub fn confirm(&self, id: &str) -> anyhow::Result<bool> {
+        let mut list = self.list.lock().unwrap();
+        let Some(subscription) = list.iter_mut().find(|s| s.id == id) else {
+            return Ok(false);
+        };
+        if !subscription.confirmed {
+            subscription.confirmed = true;
+            save_json(self.path.as_deref(), &*list)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns false if there was no subscription with this ID
+    pub fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        let mut list = self.list.lock().unwrap();
+        let len = list.len();
+        list.retain(|s| s.id != id);
+        if list.len() == len {
+            return Ok(false);
+        }
+        save_json(self.path.as_deref(), &*list)?;
+        Ok(true)
+    }
+
+    /// Send a notification to every subscriber of a changed order
+    pub async fn notify(&self, notifier: &Notifier, changes: &[OrderChange], public_url: &str) {
+        let pending: Vec<(Subscription, Notification)> = {
+            let list = self.list.lock().unwrap();
+            changes.iter().flat_map(|change| {
+                list.iter().filter(|s| s.order_id == change.order_id && s.confirmed).map(|s| {
+                    let unsubscribe_url = format!("{}/unsubscribe/{}", public_url.trim_end_matches('/'), s.id);
+                    (s.clone(), Notification::new(change, unsubscribe_url))
+                }).collect::<Vec<_>>()
+            }).collect()
+        };
+        if !pending.is_empty() {
+            info!("Sending {} notifications for {} changed orders.", pending.len(), changes.len());
+        }
+        for (subscription, notification) in &pending {
+            notifier.send(&subscription.target, notification).await;
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/subscribe", post(subscribe))
+        .route("/confirm/:id", get(confirm))
+        .route("/unsubscribe/:id", get(unsubscribe))
+}
+
+/// Subscription form on the order page, the order ID is signed into the token when the page is rendered
+#[derive(Deserialize)]
+pub struct SubscribeForm {
+    token: String,
+    notify: String
+}
+
+async fn subscribe(State(state): State<AppState>, _: RateLimit, form: Result<Form<SubscribeForm>, FormRejection>) -> impl IntoResponse {
+    let Ok(Form(form)) = form else {
+        return SubscriptionTemplate::failed(StatusCode::BAD_REQUEST, "Could not read the subscription form, please look the order up again.");
+    };
+    let order = state.form_signer.verify(&form.token).and_then(|order_id| state.orders().get_order(order_id).cloned());
+    let Some(order) = order else {
+        return SubscriptionTemplate::failed(StatusCode::NOT_FOUND, "Could not verify the order, please look it up again.");
+    };
+    let target = match Target::parse(&form.notify) {
+        Some(target) if state.notifier.accepts(&target) => target,
+        _ => return SubscriptionTemplate::failed(StatusCode::BAD_REQUEST, "Please enter an email address or an https webhook URL."),
+    };
+    if let Target::Webhook(url) = &target {
+        if let Err(err) = resolve_webhook(url).await {
+            info!("Rejected webhook subscription for order id {}: {:#}", state.redactor.order_id(order.cs_id), err);
+            return SubscriptionTemplate::failed(StatusCode::BAD_REQUEST, "The webhook URL has to point to a public https server.");
+        }
+    }
+    let message = match state.subscriptions.add(order.cs_id, target) {
+        Ok(AddResult::Added(subscription)) if !subscription.confirmed => {
+            info!("New unconfirmed subscription for order id {}.", state.redactor.order_id(order.cs_id));
+            let public_url = state.config.public_url.trim_end_matches('/');
+            let notification = Notification::confirmation(
+                order.cs_id,
+                format!("{}/confirm/{}", public_url, subscription.id),
+                format!("{}/unsubscribe/{}", public_url, subscription.id),
+            );
+            state.notifier.send(&subscription.target, &notification).await;
+            format!("We sent you an email to confirm the subscription, updates about order {} start once you open the link in it.", order.cs_id)
+        }
+        Ok(AddResult::Added(_)) => {
+            info!("New subscription for order id {}.", state.redactor.order_id(order.cs_id));
+            format!("You will be notified when the status or queue position of order {} changes.", order.cs_id)
+        }
+        Ok(AddResult::AlreadySubscribed) => format!("You are already subscribed to order {}.", order.cs_id),
+        Ok(AddResult::LimitReached) => return SubscriptionTemplate::failed(StatusCode::CONFLICT, "This order already has the maximum number of subscriptions."),
+        Err(err) => {
+            warn!("Failed to store subscription: {:#}", err);
+            return SubscriptionTemplate::failed(StatusCode::INTERNAL_SERVER_ERROR, "Could not store the subscription, please try again later.");
+        }
+    };
+    (StatusCode::OK, HtmlTemplate(SubscriptionTemplate { success: true, message }))
+}
+
+async fn confirm(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.subscriptions.confirm(&id) {
+        Ok(true) => (StatusCode::OK, HtmlTemplate(SubscriptionTemplate { success: true, message: "Thank you, you will be notified when your order moves.".to_string() })),
+        Ok(false) => SubscriptionTemplate::failed(StatusCode::NOT_FOUND, "This subscription does not exist, it might have been removed."),
+        Err(err) => {
+            warn!("Failed to confirm subscription: {:#}", err);
+            SubscriptionTemplate::failed(StatusCode::INTERNAL_SERVER_ERROR, "Could not confirm the subscription, please try again later.")
+        }
+    }
+}
+
+async fn unsubscribe(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.subscriptions.remove(&id) {
+        Ok(true) => (StatusCode::OK, HtmlTemplate(SubscriptionTemplate { success: true, message: "You will not receive any more notifications.".to_string() })),
+        Ok(false) => SubscriptionTemplate::failed(StatusCode::NOT_FOUND, "This subscription does not exist, it might have already been removed."),
+        Err(err) => {
+            warn!("Failed to remove subscription: {:#}", err);
+            SubscriptionTemplate::failed(StatusCode::INTERNAL_SERVER_ERROR, "Could not remove the subscription, please try again later.")
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "subscription.html")]
+struct SubscriptionTemplate {
+    success: bool,
+    message: String
+}
+
+impl SubscriptionTemplate {
+    fn failed(status: StatusCode, message: &str) -> (StatusCode, HtmlTemplate<Self>) {
+        (status, HtmlTemplate(Self {
+            success: false,
+            message: message.to_string()
+        }))
+    }
+}
@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+//! Requests through the full router, served from the example data
+
+use axum::{body::Body, http::{header, Request, StatusCode}, Router};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Datelike;
+use cs_data::{anonymize, glasgow_data};
+use tower::ServiceExt;
+use crate::{app, config::Config, lookup::OrderQuery, privacy::Redactor, reload, AppState};
+
+fn example_data(name: &str) -> String {
+    format!("{}/../example-data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn test_config() -> Config {
+    Config {
+        fulfillment_data: example_data("fulfillment-data.csv"),
+        production_data: example_data("production-data.csv"),
+        status_overrides: Some(example_data("status-overrides.csv")),
+        returns_data: Some(example_data("returns-data.csv")),
+        purchase_order_data: Some(example_data("purchase-order-data.csv")),
+        admin_password: None,
+        admin_token: None,
+        link_secret: Some("test-link-secret".to_string()),
+        email_verification: false,
+        log_redaction: false,
+        log_salt: None,
+        log_dir: None,
+        log_retention_days: 14,
+        reload_interval: None,
+        public_url: "http://127.0.0.1:8019".to_string(),
+        subscriptions_data: None,
+        static_dir: None,
+        feed_data: None,
+        smtp_url: None,
+        smtp_from: None,
+        notify_file: None,
+        lookup_rate_limit: 0,
+        data_max_age_hours: None,
+        shutdown_timeout: 30,
+        stalled_days: 14,
+        embargoed_countries: Vec::new(),
+    }
+}
+
+fn test_app(config: Config) -> Router {
+    let orders = reload::load_orders(&config).unwrap();
+    app(AppState::new(config, orders).unwrap())
+}
+
+/// Status and body of the response to a request
+async fn get(app: Router, request: Request<Body>) -> (StatusCode, String) {
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn request(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn index_page() {
+    let (status, body) = get(test_app(test_config()), request("/")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("<html lang=\"en\">"));
+    assert!(body.contains("Glasgow Cases"));
+    assert!(body.contains("action=\"/order\""));
+}
+
+#[tokio::test]
+async fn index_page_negotiates_language() {
+    let request = Request::get("/").header(header::ACCEPT_LANGUAGE, "de-DE,de;q=0.9,en;q=0.5").body(Body::empty()).unwrap();
+    let (status, body) = get(test_app(test_config()), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("<html lang=\"de\">"));
+}
+
+#[tokio::test]
+async fn order_page() {
+    let (status, body) = get(test_app(test_config()), request("/order?id=119012&year=2023&month=11&day=2")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Your order number 119012 has the queue id 0."));
+    assert!(body.contains("EarlyBird Glasgow Case"));
+    assert!(body.contains("Ready to ship."));
+    assert!(body.contains("1Z999AA10123456784"));
+}
+
+#[tokio::test]
+async fn order_page_post() {
+    let request = Request::post("/order")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("id=119012&year=2023&month=11&day=2"))
+        .unwrap();
+    let (status, body) = get(test_app(test_config()), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Your order number 119012 has the queue id 0."));
+    // The subscription form carries a signed token instead of the lookup fields
+    assert!(body.contains("name=\"token\""));
+    assert!(!body.contains("type=\"hidden\" name=\"day\""));
+}
+
+#[tokio::test]
+async fn order_page_ignores_email_in_query() {
+    let app = test_app(Config { email_verification: true, ..test_config() });
+    let (status, body) = get(app, request("/order?id=119012&email=backer%40example.com")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(!body.contains("backer@example.com"));
+}
+
+#[tokio::test]
+async fn order_page_wrong_date() {
+    // One day off is accepted for backers in other time zones
+    let (status, _) = get(test_app(test_config()), request("/order?id=119012&year=2023&month=11&day=3")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = get(test_app(test_config()), request("/order?id=119012&year=2023&month=11&day=4")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(!body.contains("Your order number 119012 has"));
+}
+
+#[tokio::test]
+async fn order_page_invalid_form() {
+    let (status, body) = get(test_app(test_config()), request("/order?id=&year=2023&month=11&day=2")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Please enter your order number."));
+}
+
+#[tokio::test]
+async fn order_api() {
+    let (status, body) = get(test_app(test_config()), request("/api/order?id=120478&year=2024&month=2&day=14")).await;
+    assert_eq!(status, StatusCode::OK);
+    let order: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(order["cs_id"], 120478);
+    assert!(order.get("email_hash").is_none());
+}
+
+#[tokio::test]
+async fn order_lookups_are_rate_limited() {
+    let app = test_app(Config { lookup_rate_limit: 2, ..test_config() });
+    for _ in 0..2 {
+        let (status, _) = get(app.clone(), request("/order?id=119012&year=2023&month=11&day=2")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let response = app.oneshot(request("/order?id=119012&year=2023&month=11&day=2")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[tokio::test]
+async fn failed_admin_logins_are_rate_limited() {
+    let app = test_app(Config { admin_password: Some("secret".to_string()), ..test_config() });
+    let admin = |password: &str| Request::get("/admin")
+        .header(header::AUTHORIZATION, format!("Basic {}", STANDARD.encode(format!("admin:{}", password))))
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = get(app.clone(), admin("secret")).await;
+    assert_eq!(status, StatusCode::OK);
+    for _ in 0..crate::auth::FAILED_LOGIN_LIMIT {
+        let (status, _) = get(app.clone(), admin("wrong")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let response = app.oneshot(admin("secret")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[test]
+fn anonymized_orders_can_not_be_looked_up() {
+    let config = test_config();
+    let orders = reload::load_orders(&config).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let anonymized = dir.path().join("anonymized.csv");
+    anonymize::anonymize_order_data(&config.fulfillment_data, anonymized.to_str().unwrap()).unwrap();
+    let anonymized = glasgow_data::Orders::new(anonymized.to_str().unwrap(), &config.production_data).unwrap();
+    assert_eq!(anonymized.orders.len(), orders.orders.len());
+    let redactor = Redactor::new(false, None);
+    for o in &anonymized.orders {
+        let query = OrderQuery { id: o.cs_id, year: Some(o.date.year()), month: Some(o.date.month()), day: Some(o.date.day()), email: None };
+        assert!(query.verify(&anonymized, false, &redactor).is_some());
+        assert!(query.verify(&orders, false, &redactor).is_none(), "anonymized order {} matches a real order", o.cs_id);
+    }
+}
+
+#[tokio::test]
+async fn badges_and_widget_without_orders() {
+    let dir = tempfile::tempdir().unwrap();
+    let fulfillment_data = dir.path().join("fulfillment-data.csv");
+    std::fs::write(&fulfillment_data, "Order ID,Part Number,Product Name,Qty,Unit Price,Subtotal,Company,Country Code,Placed Time,Shipped Time,Tracking\n").unwrap();
+    let config = Config {
+        fulfillment_data: fulfillment_data.to_str().unwrap().to_string(),
+        status_overrides: None,
+        returns_data: None,
+        purchase_order_data: None,
+        ..test_config()
+    };
+    let app = test_app(config);
+    let (status, body) = get(app.clone(), request("/badge/glasgow-case-at-mouser.svg")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(">0%<"));
+    let (status, body) = get(app, request("/widget")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains("NaN%") && !body.contains("inf%"));
+}
+
+/// Token of the subscription form on an order page
+fn subscribe_token(body: &str) -> String {
+    let start = body.find("name=\"token\" value=\"").unwrap() + "name=\"token\" value=\"".len();
+    body[start..].split('"').next().unwrap().to_string()
+}
+
+fn form_post(uri: &str, body: String) -> Request<Body> {
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn email_subscriptions_need_confirmation() {
+    let dir = tempfile::tempdir().unwrap();
+    let notify_file = dir.path().join("notifications.jsonl");
+    let app = test_app(Config { notify_file: Some(notify_file.to_str().unwrap().to_string()), ..test_config() });
+    let (_, body) = get(app.clone(), request("/order?id=120478&year=2024&month=2&day=14")).await;
+    let form = format!("token={}&notify=backer%40example.com", subscribe_token(&body));
+    let (status, body) = get(app.clone(), form_post("/subscribe", form)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("confirm"));
+
+    let sent = std::fs::read_to_string(&notify_file).unwrap();
+    let confirmation: serde_json::Value = serde_json::from_str(sent.lines().next().unwrap()).unwrap();
+    assert_eq!(confirmation["target"]["address"], "backer@example.com");
+    assert!(confirmation.get("change").is_none());
+    let text = confirmation["text"].as_str().unwrap();
+    let confirm_url = text.split_whitespace().find(|w| w.contains("/confirm/")).unwrap();
+    let confirm_path = &confirm_url[confirm_url.find("/confirm/").unwrap()..];
+    let (status, _) = get(app.clone(), request(confirm_path)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = get(app, request("/confirm/no-such-subscription")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn webhooks_to_internal_addresses_are_rejected() {
+    let app = test_app(test_config());
+    let (_, body) = get(app.clone(), request("/order?id=120478&year=2024&month=2&day=14")).await;
+    let token = subscribe_token(&body);
+    for url in ["https://127.0.0.1/hook", "https://10.1.2.3/hook", "https://169.254.169.254/latest", "https://[::1]/hook", "https://[fd00::1]/hook", "https://localhost/hook"] {
+        let form = format!("token={}&notify={}", token, url);
+        let (status, body) = get(app.clone(), form_post("/subscribe", form)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", url);
+        assert!(body.contains("public https server"), "{}", url);
+    }
+}
+
+#[tokio::test]
+async fn health_does_not_publish_reload_errors() {
+    let config = test_config();
+    let orders = reload::load_orders(&config).unwrap();
+    let state = AppState::new(config, orders).unwrap();
+    state.health.reload_failed("could not read /srv/private/fulfillment-data.csv".to_string());
+    let (status, body) = get(app(state), request("/readyz")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["status"], "reload_failed");
+    assert_eq!(report["reload_failed"], true);
+    assert!(!body.contains("/srv/private"));
+}
+
+fn admin_post(uri: &str, origin: &str, content_type: &str, body: String) -> Request<Body> {
+    Request::post(uri)
+        .header(header::AUTHORIZATION, format!("Basic {}", STANDARD.encode("admin:secret")))
+        .header(header::HOST, "127.0.0.1:8019")
+        .header(header::ORIGIN, origin)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn admin_posts_check_origin_against_public_url() {
+    let app = test_app(Config { admin_password: Some("secret".to_string()), ..test_config() });
+    let (status, _) = get(app.clone(), admin_post("/admin/reload", "http://evil.example", "text/plain", String::new())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // The Host header is chosen by the client and does not count
+    let (status, _) = get(app.clone(), admin_post("/admin/reload", "http://127.0.0.1:8019.evil.example", "text/plain", String::new())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = get(app, admin_post("/admin/reload", "http://127.0.0.1:8019", "text/plain", String::new())).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn admin_upload_rejects_duplicate_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let status_overrides = dir.path().join("status-overrides.csv");
+    std::fs::copy(example_data("status-overrides.csv"), &status_overrides).unwrap();
+    let app = test_app(Config {
+        admin_password: Some("secret".to_string()),
+        status_overrides: Some(status_overrides.to_str().unwrap().to_string()),
+        ..test_config()
+    });
+    let csv = std::fs::read_to_string(&status_overrides).unwrap();
+    let part = format!("--b\r\nContent-Disposition: form-data; name=\"status_overrides\"; filename=\"a.csv\"\r\n\r\n{}\r\n", csv);
+    let body = format!("{}{}--b--\r\n", part, part);
+    let (status, body) = get(app, admin_post("/admin/upload", "http://127.0.0.1:8019", "multipart/form-data; boundary=b", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("status_overrides was uploaded more than once."));
+    let files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(files, ["status-overrides.csv"]);
+}
+
+#[tokio::test]
+async fn unknown_page() {
+    let (status, body) = get(test_app(test_config()), request("/no-such-page")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body.contains("Page not found"));
+}
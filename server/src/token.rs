@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: 2024 1BitSquared <info@1bitsquared.com>
+// SPDX-FileContributor: Written by Piotr Esden-Tempski <piotr@1bitsquared.com>
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bytes of the HMAC kept in the token, enough to make guessing infeasible while keeping links short
+const MAC_LEN: usize = 16;
+
+/// Signs and verifies opaque order status link tokens
+///
+/// A token is the order ID followed by a truncated HMAC of it, so a valid
+/// token proves that the holder passed the order date check at some point
+/// without the date itself ending up in URLs or logs.
+#[derive(Clone)]
+pub struct LinkSigner {
+    secret: Vec<u8>
+}
+
+impl LinkSigner {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec()
+        }
+    }
+
+    /// Signer with a random key, its tokens are only valid until the process exits
+    pub fn random() -> Self {
+        Self {
+            secret: rand::random::<[u8; 32]>().to_vec()
+        }
+    }
+
+    fn mac(&self, order_id: usize) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(b"order-status:");
+        mac.update(&(order_id as u64).to_be_bytes());
+        mac
+    }
+
+    pub fn sign(&self, order_id: usize) -> String {
+        let mut token = (order_id as u64).to_be_bytes().to_vec();
+        token.extend_from_slice(&self.mac(order_id).finalize().into_bytes()[..MAC_LEN]);
+        URL_SAFE_NO_PAD.encode(token)
+    }
+
+    /// Return the order ID of a token if the signature is valid
+    pub fn verify(&self, token: &str) -> Option<usize> {
+        let token = URL_SAFE_NO_PAD.decode(token).ok()?;
+        if token.len() != 8 + MAC_LEN {
+            return None;
+        }
+        let (id, tag) = token.split_at(8);
+        let order_id = u64::from_be_bytes(id.try_into().ok()?) as usize;
+        self.mac(order_id).verify_truncated_left(tag).ok()?;
+        Some(order_id)
+    }
+}